@@ -0,0 +1,310 @@
+//! Previews the animations of a sprite sheet without running the game.
+//!
+//! Usage: `cargo run -p anim_preview -- <sheet> [assets folder]`
+//!
+//! The sheet can be any file loaded by the `SpriteAnimationPlugin` (`.anim.ron`, `.anim.json` or
+//! `.aseprite.json`), relative to the assets folder, which defaults to `game/assets`. The sheet is
+//! reloaded whenever its file changes.
+
+use std::{env, fmt::Write, process};
+
+use bevy::prelude::*;
+use sprite_animator::{
+    SpriteAnimController, SpriteAnimData, SpriteAnimLibrary, SpriteAnimMode, SpriteAnimSet,
+    SpriteAnimSheet, SpriteAnimSheetBundle, SpriteAnimationPlugin,
+};
+
+/// Assets folder used when none is given, relative to this crate
+const DEFAULT_ASSETS: &str = "../game/assets";
+
+/// Scale of the previewed sprite, as pixel art sheets are usually small
+const SPRITE_SCALE: f32 = 4.0;
+
+/// Playback speed added or removed by the +/- keys, which is also the slowest speed
+const SPEED_STEP: f32 = 0.25;
+
+const CONTROLS: &str = "Up/Down: animation | Space: pause | Left/Right: step frame\n\
+                        +/-: speed | M: mode | R: reverse";
+
+#[derive(Resource)]
+struct Preview {
+    path: String,
+    sheet: Handle<SpriteAnimSheet>,
+    /// Animations of the sheet sorted by name, including the changes made in the preview
+    animations: Vec<SpriteAnimData>,
+    default: Option<String>,
+    selected: usize,
+}
+
+impl Preview {
+    /// Creates a library holding the animations of the preview
+    fn library(&self) -> SpriteAnimLibrary {
+        let library: SpriteAnimLibrary = self.animations.iter().cloned().collect();
+        match &self.default {
+            Some(default) => library.with_default(default),
+            None => library,
+        }
+    }
+}
+
+/// Text displaying the state of the preview
+#[derive(Component)]
+struct InfoText;
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let Some(path) = args.next() else {
+        eprintln!("Usage: anim_preview <sheet> [assets folder]");
+        eprintln!("Example: anim_preview oak_woods/character/char_blue.anim.ron");
+        process::exit(1);
+    };
+    let assets = args
+        .next()
+        .unwrap_or_else(|| DEFAULT_ASSETS.to_string());
+
+    App::new()
+        .add_plugins(
+            DefaultPlugins
+                .set(ImagePlugin::default_nearest())
+                .set(AssetPlugin {
+                    file_path: assets,
+                    ..default()
+                })
+                .set(WindowPlugin {
+                    primary_window: Some(Window {
+                        title: format!("Animation preview - {}", path),
+                        ..default()
+                    }),
+                    ..default()
+                }),
+        )
+        .add_plugins(SpriteAnimationPlugin::default())
+        .insert_resource(ClearColor(Color::srgb(0.2, 0.2, 0.25)))
+        .insert_resource(Preview {
+            path,
+            sheet: Handle::default(),
+            animations: Vec::new(),
+            default: None,
+            selected: 0,
+        })
+        .add_systems(Startup, setup)
+        .add_systems(
+            Update,
+            (
+                (load_animations, handle_input)
+                    .chain()
+                    .before(SpriteAnimSet::Prepare),
+                update_info.after(SpriteAnimSet::Apply),
+            ),
+        )
+        .run();
+}
+
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>, mut preview: ResMut<Preview>) {
+    preview.sheet = asset_server.load(preview.path.clone());
+
+    commands.spawn(Camera2dBundle::default());
+    commands.spawn(SpriteAnimSheetBundle {
+        sprite_bundle: SpriteBundle {
+            transform: Transform::from_scale(Vec3::splat(SPRITE_SCALE)),
+            ..default()
+        },
+        sheet: preview.sheet.clone(),
+    });
+    commands.spawn((
+        TextBundle::from_section(
+            "Loading...",
+            TextStyle {
+                font_size: 20.0,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(12.0),
+            left: Val::Px(12.0),
+            ..default()
+        }),
+        InfoText,
+    ));
+}
+
+/// Lists the animations of the sheet once loaded, and again whenever its file changes
+fn load_animations(
+    mut preview: ResMut<Preview>,
+    mut asset_events: EventReader<AssetEvent<SpriteAnimSheet>>,
+    sheets: Res<Assets<SpriteAnimSheet>>,
+) {
+    let modified = asset_events
+        .read()
+        .any(|event| event.is_modified(&preview.sheet));
+    if !preview.animations.is_empty() && !modified {
+        return;
+    }
+    let Some(sheet) = sheets.get(&preview.sheet) else {
+        return;
+    };
+
+    let mut names: Vec<&str> = sheet
+        .library
+        .names()
+        .filter(|name| *name != "Default")
+        .collect();
+    names.sort();
+    let animations: Vec<SpriteAnimData> = names
+        .into_iter()
+        .filter_map(|name| sheet.library.get(name).cloned())
+        .collect();
+
+    let current = preview
+        .animations
+        .get(preview.selected)
+        .or(sheet
+            .default
+            .as_ref()
+            .and_then(|default| sheet.library.get(default)))
+        .map(|anim| anim.name().to_string());
+    preview.selected = current
+        .and_then(|current| {
+            animations
+                .iter()
+                .position(|anim| anim.name() == current)
+        })
+        .unwrap_or(0);
+    preview.animations = animations;
+    preview.default = sheet.default.clone();
+}
+
+fn handle_input(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut preview: ResMut<Preview>,
+    mut query: Query<&mut SpriteAnimController>,
+) {
+    let Ok(mut ac) = query.get_single_mut() else {
+        return;
+    };
+    let count = preview.animations.len();
+    if count == 0 {
+        return;
+    }
+
+    if keys.any_just_pressed([KeyCode::ArrowUp, KeyCode::ArrowDown]) {
+        preview.selected = if keys.just_pressed(KeyCode::ArrowUp) {
+            (preview.selected + count - 1) % count
+        } else {
+            (preview.selected + 1) % count
+        };
+        ac.restart(preview.animations[preview.selected].name())
+            .ok();
+    }
+
+    if keys.just_pressed(KeyCode::Space) {
+        if ac.is_paused() {
+            ac.resume();
+        } else {
+            ac.pause();
+        }
+    }
+
+    if keys.any_just_pressed([KeyCode::ArrowLeft, KeyCode::ArrowRight]) {
+        let len = ac.current().frames().len().max(1);
+        let index = if keys.just_pressed(KeyCode::ArrowLeft) {
+            (ac.frame_index() + len - 1) % len
+        } else {
+            (ac.frame_index() + 1) % len
+        };
+        ac.pause();
+        ac.seek(index);
+    }
+
+    if keys.any_just_pressed([KeyCode::Equal, KeyCode::NumpadAdd]) {
+        let speed = ac.speed() + SPEED_STEP;
+        ac.set_speed(speed);
+    }
+    if keys.any_just_pressed([KeyCode::Minus, KeyCode::NumpadSubtract]) {
+        let speed = (ac.speed() - SPEED_STEP).max(SPEED_STEP);
+        ac.set_speed(speed);
+    }
+
+    if keys.just_pressed(KeyCode::KeyR) {
+        let reversed = !ac.is_reversed();
+        ac.set_reversed(reversed);
+    }
+
+    if keys.just_pressed(KeyCode::KeyM) {
+        let selected = preview.selected;
+        let anim = preview.animations[selected].clone();
+        let mode = next_mode(anim.mode());
+        preview.animations[selected] = anim.with_mode(mode);
+        ac.set_library(preview.library());
+    }
+}
+
+/// Returns the mode following `mode`, to cycle through every mode
+fn next_mode(mode: &SpriteAnimMode) -> SpriteAnimMode {
+    match mode {
+        SpriteAnimMode::Repeating => SpriteAnimMode::Once,
+        SpriteAnimMode::Once => SpriteAnimMode::Mirror,
+        SpriteAnimMode::Mirror => SpriteAnimMode::MirrorOnce,
+        SpriteAnimMode::MirrorOnce => SpriteAnimMode::Loop(3),
+        SpriteAnimMode::Loop(_) => SpriteAnimMode::LoopFrom(1),
+        SpriteAnimMode::LoopFrom(_) => SpriteAnimMode::Random,
+        SpriteAnimMode::Random => SpriteAnimMode::Repeating,
+    }
+}
+
+fn update_info(
+    preview: Res<Preview>,
+    controllers: Query<&SpriteAnimController>,
+    mut texts: Query<&mut Text, With<InfoText>>,
+) {
+    let Ok(mut text) = texts.get_single_mut() else {
+        return;
+    };
+    let Ok(ac) = controllers.get_single() else {
+        return;
+    };
+
+    let mut info = format!("{}\n\n", preview.path);
+    for (index, anim) in preview.animations.iter().enumerate() {
+        let cursor = if index == preview.selected { ">" } else { " " };
+        writeln!(info, "{} {}", cursor, anim.name()).ok();
+    }
+
+    let anim = ac.current();
+    let base_fps = match anim.fps() {
+        Some(fps) => format!("{} (animation)", fps),
+        None => format!("{}", ac.fps()),
+    };
+    // Rate the displayed frame is played at, including its own duration and the speed
+    let fps = ac.speed() / ac.frame_duration(ac.frame_index()).as_secs_f32();
+    writeln!(
+        info,
+        "\nFrame: {}/{} (sheet frame {})",
+        ac.frame_index() + 1,
+        anim.frames().len(),
+        ac.frame()
+    )
+    .ok();
+    writeln!(info, "FPS: {:.1}", fps).ok();
+    writeln!(info, "Base FPS: {}", base_fps).ok();
+    writeln!(info, "Speed: x{}", ac.speed()).ok();
+    writeln!(info, "Mode: {:?}", anim.mode()).ok();
+    for marker in anim.markers(ac.frame_index()) {
+        writeln!(info, "Marker: {}", marker).ok();
+    }
+    let mut status = Vec::new();
+    if ac.is_paused() {
+        status.push("Paused");
+    }
+    if ac.is_reversed() {
+        status.push("Reversed");
+    }
+    if ac.is_finished() {
+        status.push("Finished");
+    }
+    writeln!(info, "{}", status.join(" | ")).ok();
+    write!(info, "\n{}", CONTROLS).ok();
+
+    text.sections[0].value = info;
+}
@@ -1,6 +1,17 @@
 use bevy::prelude::*;
 
 use crate::components::{damage::Damage, health::Health, player::Player};
+#[cfg(debug_assertions)]
+use crate::movement::abilities::{Ability, AirJumps, GrantAbility, WallJump, WallSlide};
+
+#[cfg(debug_assertions)]
+const DEBUG_AIR_JUMPS: u32 = 1;
+#[cfg(debug_assertions)]
+const DEBUG_WALL_SLIDE_MAX_FALL_SPEED: f32 = 40.0;
+#[cfg(debug_assertions)]
+const DEBUG_WALL_JUMP_HORIZONTAL_IMPULSE: f32 = 120.0;
+#[cfg(debug_assertions)]
+const DEBUG_WALL_JUMP_VERTICAL_IMPULSE: f32 = 130.0;
 
 ///This plugin provides terminal debug capabilities
 ///By adding systems, requested information will be printed to the terminal
@@ -10,6 +21,9 @@ pub struct DebugPlugin;
 impl Plugin for DebugPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Startup, print_debug_active_message);
+        // Cheats are left out of release builds
+        #[cfg(debug_assertions)]
+        app.add_systems(Update, grant_all_abilities);
     }
 }
 
@@ -18,3 +32,30 @@ impl Plugin for DebugPlugin {
 fn print_debug_active_message() {
     info!("The debugger is active!");
 }
+
+///Function that grants every traversal ability to the players when F1 is pressed,
+///to try them before they can be unlocked in game. Only available in debug builds
+#[cfg(debug_assertions)]
+fn grant_all_abilities(
+    mut grant_event_writer: EventWriter<GrantAbility>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    query: Query<Entity, With<Player>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::F1) {
+        return;
+    }
+
+    for entity in query.iter() {
+        for ability in [
+            Ability::AirJumps(AirJumps::new(DEBUG_AIR_JUMPS)),
+            Ability::WallSlide(WallSlide::new(DEBUG_WALL_SLIDE_MAX_FALL_SPEED)),
+            Ability::WallJump(WallJump::new(
+                DEBUG_WALL_JUMP_HORIZONTAL_IMPULSE,
+                DEBUG_WALL_JUMP_VERTICAL_IMPULSE,
+            )),
+        ] {
+            grant_event_writer.send(GrantAbility { entity, ability });
+        }
+        info!("Granted every ability to {:?}", entity);
+    }
+}
@@ -0,0 +1,767 @@
+use core::fmt;
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    input::{gamepad::GamepadConnectionEvent, InputSystem},
+    prelude::*,
+    utils::HashMap,
+};
+use serde::{Deserialize, Serialize};
+
+/// Input map loaded on startup, relative to the assets folder
+const INPUT_MAP_PATH: &str = "controls.input.ron";
+
+/// Deadzone of the gamepad axes of actions that do not set their own
+const DEFAULT_DEADZONE: f32 = 0.1;
+
+/// Plugin turning keyboard and gamepad input into logical [`InputAction`]s.
+///
+/// Bindings are read from `assets/controls.input.ron` and can be changed at runtime through the
+/// [`InputMap`] resource. Changes made to the file while the game runs replace the runtime
+/// bindings, see [`InputMap::rebind`].
+///
+/// Each controlled entity reads the devices of its [`InputScheme`], and systems read the state
+/// of its actions from its [`ActionState`]. Schemes are assigned by the plugin whenever an
+/// entity gets or loses one, or a gamepad is connected or disconnected, see
+/// [`InputScheme::assign`]. Entities can also hold their own [`InputMap`], for
+/// example to give two players sharing a keyboard different keys.
+pub struct InputMapPlugin;
+
+impl Plugin for InputMapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<InputMap>()
+            .init_asset_loader::<InputMapLoader>()
+            .init_resource::<InputMap>()
+            .add_systems(Startup, load_input_map)
+            .add_systems(
+                PreUpdate,
+                (apply_input_map, assign_input_schemes, update_action_state)
+                    .chain()
+                    .after(InputSystem),
+            );
+    }
+}
+
+/// A logical action the player can perform, independently of the input device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum InputAction {
+    /// Horizontal movement, from -1 (left) to 1 (right)
+    Move,
+    Jump,
+    Attack,
+    Dash,
+    Interact,
+    Pause,
+}
+
+impl InputAction {
+    pub const ALL: [InputAction; 6] = [
+        InputAction::Move,
+        InputAction::Jump,
+        InputAction::Attack,
+        InputAction::Dash,
+        InputAction::Interact,
+        InputAction::Pause,
+    ];
+}
+
+/// A keyboard key, gamepad button or gamepad axis bound to an [`InputAction`].
+/// Buttons give a value of 1 when pressed, and [`InputBinding::NegativeKey`] gives -1.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum InputBinding {
+    Key(KeyCode),
+    /// Key moving axis actions, such as [`InputAction::Move`], towards negative values
+    NegativeKey(KeyCode),
+    GamepadButton(GamepadButtonType),
+    GamepadAxis(GamepadAxisType),
+}
+
+/// Bindings of every [`InputAction`], loaded from a `.input.ron` file.
+/// Used as a resource by every entity, unless it holds its own `InputMap` component.
+///
+/// Gamepad axes closer to 0 than the deadzone of their action are read as 0, which defaults to
+/// 0.1 for actions without their own `deadzones` entry.
+///
+/// Example file (`controls.input.ron`):
+/// ```ron
+/// (
+///     bindings: {
+///         Move: [NegativeKey(KeyA), Key(KeyD), GamepadAxis(LeftStickX)],
+///         Jump: [Key(Space), GamepadButton(South)],
+///     },
+///     deadzones: {
+///         Move: 0.2,
+///     },
+/// )
+/// ```
+///
+/// Example usage:
+/// ```Rust
+/// fn rebind_jump(mut input_map: ResMut<InputMap>) {
+///     input_map.rebind(
+///         InputAction::Jump,
+///         InputBinding::Key(KeyCode::Space),
+///         InputBinding::Key(KeyCode::KeyW),
+///     );
+/// }
+/// ```
+#[derive(Asset, Resource, Component, TypePath, Debug, Clone, Serialize, Deserialize)]
+pub struct InputMap {
+    bindings: HashMap<InputAction, Vec<InputBinding>>,
+    #[serde(default)]
+    deadzones: HashMap<InputAction, f32>,
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        Self::new()
+            .with_binding(InputAction::Move, InputBinding::NegativeKey(KeyCode::KeyA))
+            .with_binding(InputAction::Move, InputBinding::Key(KeyCode::KeyD))
+            .with_binding(
+                InputAction::Move,
+                InputBinding::NegativeKey(KeyCode::ArrowLeft),
+            )
+            .with_binding(InputAction::Move, InputBinding::Key(KeyCode::ArrowRight))
+            .with_binding(
+                InputAction::Move,
+                InputBinding::GamepadAxis(GamepadAxisType::LeftStickX),
+            )
+            .with_binding(InputAction::Jump, InputBinding::Key(KeyCode::Space))
+            .with_binding(
+                InputAction::Jump,
+                InputBinding::GamepadButton(GamepadButtonType::South),
+            )
+            .with_binding(InputAction::Attack, InputBinding::Key(KeyCode::KeyJ))
+            .with_binding(
+                InputAction::Attack,
+                InputBinding::GamepadButton(GamepadButtonType::West),
+            )
+            .with_binding(InputAction::Dash, InputBinding::Key(KeyCode::ShiftLeft))
+            .with_binding(
+                InputAction::Dash,
+                InputBinding::GamepadButton(GamepadButtonType::RightTrigger),
+            )
+            .with_binding(InputAction::Interact, InputBinding::Key(KeyCode::KeyE))
+            .with_binding(
+                InputAction::Interact,
+                InputBinding::GamepadButton(GamepadButtonType::North),
+            )
+            .with_binding(InputAction::Pause, InputBinding::Key(KeyCode::Escape))
+            .with_binding(
+                InputAction::Pause,
+                InputBinding::GamepadButton(GamepadButtonType::Start),
+            )
+    }
+}
+
+impl InputMap {
+    /// Creates an input map without any binding
+    pub fn new() -> Self {
+        Self {
+            bindings: HashMap::new(),
+            deadzones: HashMap::new(),
+        }
+    }
+
+    /// Adds a binding to `action` on instantiation
+    pub fn with_binding(mut self, action: InputAction, binding: InputBinding) -> Self {
+        self.bind(action, binding);
+        self
+    }
+
+    /// Adds a binding to `action`, keeping its other bindings
+    pub fn bind(&mut self, action: InputAction, binding: InputBinding) {
+        let bindings = self.bindings.entry(action).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    /// Removes a binding from `action`
+    pub fn unbind(&mut self, action: InputAction, binding: InputBinding) {
+        if let Some(bindings) = self.bindings.get_mut(&action) {
+            bindings.retain(|other| *other != binding);
+        }
+    }
+
+    /// Replaces the `old` binding of `action` with `new`, keeping its other bindings, such as
+    /// the keys moving the other way. Adds `new` if `action` is not bound to `old`.
+    ///
+    /// Changes made to the [`InputMap`] resource are lost when `controls.input.ron` is
+    /// hot-reloaded, as the file replaces the whole resource. Rebinds meant to last should also
+    /// be written to the file.
+    pub fn rebind(&mut self, action: InputAction, old: InputBinding, new: InputBinding) {
+        let bindings = self.bindings.entry(action).or_default();
+        bindings.retain(|other| *other != new);
+        match bindings.iter().position(|other| *other == old) {
+            Some(index) => bindings[index] = new,
+            None => bindings.push(new),
+        }
+    }
+
+    /// Removes every binding of `action`
+    pub fn clear(&mut self, action: InputAction) {
+        self.bindings.remove(&action);
+    }
+
+    /// Sets the deadzone of the gamepad axes bound to `action` on instantiation
+    pub fn with_deadzone(mut self, action: InputAction, deadzone: f32) -> Self {
+        self.set_deadzone(action, deadzone);
+        self
+    }
+
+    /// Sets the deadzone of the gamepad axes bound to `action`: values closer to 0 are read as 0
+    pub fn set_deadzone(&mut self, action: InputAction, deadzone: f32) {
+        self.deadzones.insert(action, deadzone.abs());
+    }
+
+    /// Returns the deadzone of the gamepad axes bound to `action`
+    pub fn deadzone(&self, action: InputAction) -> f32 {
+        self.deadzones
+            .get(&action)
+            .copied()
+            .unwrap_or(DEFAULT_DEADZONE)
+    }
+
+    /// Returns the bindings of `action`
+    pub fn bindings(&self, action: InputAction) -> &[InputBinding] {
+        self.bindings
+            .get(&action)
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+
+    /// Returns the value of `action` from the current input of the devices of `scheme`,
+    /// clamped between -1 and 1. Gamepad axes inside the deadzone of `action` are ignored.
+    pub fn value(
+        &self,
+        action: InputAction,
+        scheme: InputScheme,
+        keys: &ButtonInput<KeyCode>,
+        gamepads: &Gamepads,
+        buttons: &ButtonInput<GamepadButton>,
+        axes: &Axis<GamepadAxis>,
+    ) -> f32 {
+        let deadzone = self.deadzone(action);
+        let value: f32 = self
+            .bindings(action)
+            .iter()
+            .map(|binding| match *binding {
+                _ if !scheme.uses_device(binding.device()) => 0.0,
+                InputBinding::Key(key) => keys.pressed(key) as u8 as f32,
+                InputBinding::NegativeKey(key) => -(keys.pressed(key) as u8 as f32),
+                InputBinding::GamepadButton(button_type) => gamepads
+                    .iter()
+                    .filter(|gamepad| scheme.uses_gamepad(*gamepad))
+                    .any(|gamepad| buttons.pressed(GamepadButton::new(gamepad, button_type)))
+                    as u8 as f32,
+                InputBinding::GamepadAxis(axis_type) => gamepads
+                    .iter()
+                    .filter(|gamepad| scheme.uses_gamepad(*gamepad))
+                    .filter_map(|gamepad| axes.get(GamepadAxis::new(gamepad, axis_type)))
+                    .filter(|value| value.abs() >= deadzone)
+                    .sum(),
+            })
+            .sum();
+        value.clamp(-1.0, 1.0)
+    }
+}
+
+/// Device read by an [`InputBinding`]
+#[derive(PartialEq, Eq)]
+enum InputDevice {
+    Keyboard,
+    GamepadButtons,
+    GamepadAxes,
+}
+
+impl InputBinding {
+    fn device(&self) -> InputDevice {
+        match self {
+            Self::Key(_) | Self::NegativeKey(_) => InputDevice::Keyboard,
+            Self::GamepadButton(_) => InputDevice::GamepadButtons,
+            Self::GamepadAxis(_) => InputDevice::GamepadAxes,
+        }
+    }
+}
+
+/// Devices read to control an entity. Local co-op players each use their own scheme, such as
+/// the keyboard for one player and a gamepad for the other.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InputScheme {
+    /// The keyboard and every gamepad
+    #[default]
+    All,
+    Keyboard,
+    Gamepad(Gamepad),
+}
+
+impl InputScheme {
+    /// Returns the `current` schemes of the controlled entities, reassigned to the connected
+    /// `gamepads`. A lone entity reads every device. Otherwise each entity keeps its device
+    /// while it is connected, and the others get the first free one: the keyboard, then each
+    /// gamepad by id. Once every device is taken, the remaining entities share the keyboard.
+    pub fn assign(current: &[InputScheme], gamepads: &Gamepads) -> Vec<InputScheme> {
+        if current.len() <= 1 {
+            return vec![Self::All; current.len()];
+        }
+
+        let mut connected: Vec<Gamepad> = gamepads.iter().collect();
+        connected.sort_by_key(|gamepad| gamepad.id);
+        let mut taken = Vec::new();
+        let mut kept = Vec::new();
+        for scheme in current {
+            let available = match scheme {
+                Self::All => false,
+                Self::Keyboard => true,
+                Self::Gamepad(gamepad) => connected.contains(gamepad),
+            };
+            if available && !taken.contains(scheme) {
+                taken.push(*scheme);
+                kept.push(Some(*scheme));
+            } else {
+                kept.push(None);
+            }
+        }
+
+        kept.into_iter()
+            .map(|scheme| {
+                scheme.unwrap_or_else(|| {
+                    let free = std::iter::once(Self::Keyboard)
+                        .chain(connected.iter().copied().map(Self::Gamepad))
+                        .find(|scheme| !taken.contains(scheme))
+                        .unwrap_or_else(|| {
+                            warn!("No free input device left, sharing the keyboard");
+                            Self::Keyboard
+                        });
+                    taken.push(free);
+                    free
+                })
+            })
+            .collect()
+    }
+
+    fn uses_device(&self, device: InputDevice) -> bool {
+        match self {
+            Self::All => true,
+            Self::Keyboard => device == InputDevice::Keyboard,
+            Self::Gamepad(_) => device != InputDevice::Keyboard,
+        }
+    }
+
+    fn uses_gamepad(&self, gamepad: Gamepad) -> bool {
+        match self {
+            Self::All => true,
+            Self::Keyboard => false,
+            Self::Gamepad(own) => *own == gamepad,
+        }
+    }
+}
+
+/// State of every [`InputAction`] of an entity, updated from its [`InputScheme`] every frame.
+#[derive(Component, Debug, Default)]
+pub struct ActionState {
+    values: HashMap<InputAction, f32>,
+    previous: HashMap<InputAction, f32>,
+}
+
+impl ActionState {
+    /// Returns the value of `action`, between -1 and 1
+    pub fn value(&self, action: InputAction) -> f32 {
+        self.values.get(&action).copied().unwrap_or(0.0)
+    }
+
+    /// Returns true while `action` is held
+    pub fn pressed(&self, action: InputAction) -> bool {
+        self.value(action) != 0.0
+    }
+
+    /// Returns true on the frame `action` starts being held
+    pub fn just_pressed(&self, action: InputAction) -> bool {
+        self.pressed(action) && self.previous.get(&action).copied().unwrap_or(0.0) == 0.0
+    }
+
+    /// Returns true on the frame `action` stops being held
+    pub fn just_released(&self, action: InputAction) -> bool {
+        !self.pressed(action) && self.previous.get(&action).copied().unwrap_or(0.0) != 0.0
+    }
+}
+
+/// Bundle of components required for an entity to be controlled by a player
+#[derive(Bundle, Default)]
+pub struct PlayerInputBundle {
+    pub scheme: InputScheme,
+    pub action_state: ActionState,
+}
+
+impl PlayerInputBundle {
+    pub fn new(scheme: InputScheme) -> Self {
+        Self {
+            scheme,
+            action_state: ActionState::default(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum InputMapLoaderError {
+    Io(std::io::Error),
+    Ron(ron::error::SpannedError),
+}
+
+impl fmt::Display for InputMapLoaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "Could not read the input map: {}", err),
+            Self::Ron(err) => write!(f, "Could not parse the input map: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for InputMapLoaderError {}
+
+impl From<std::io::Error> for InputMapLoaderError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<ron::error::SpannedError> for InputMapLoaderError {
+    fn from(err: ron::error::SpannedError) -> Self {
+        Self::Ron(err)
+    }
+}
+
+/// Loads [`InputMap`] assets from `.input.ron` files
+#[derive(Default)]
+pub struct InputMapLoader;
+
+impl AssetLoader for InputMapLoader {
+    type Asset = InputMap;
+    type Settings = ();
+    type Error = InputMapLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(ron::de::from_bytes(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["input.ron"]
+    }
+}
+
+/// Handle to the input map file, kept to pick up its changes
+#[derive(Resource)]
+struct InputMapHandle(Handle<InputMap>);
+
+/// load_input_map starts loading the input map file
+fn load_input_map(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(InputMapHandle(asset_server.load(INPUT_MAP_PATH)));
+}
+
+/// apply_input_map replaces the [`InputMap`] resource once the file is loaded, and every time
+/// it changes. The default bindings are used until then, or if the file cannot be loaded.
+fn apply_input_map(
+    mut asset_events: EventReader<AssetEvent<InputMap>>,
+    handle: Option<Res<InputMapHandle>>,
+    input_maps: Res<Assets<InputMap>>,
+    mut input_map: ResMut<InputMap>,
+) {
+    let Some(handle) = handle else {
+        return;
+    };
+    for event in asset_events.read() {
+        if event.is_loaded_with_dependencies(&handle.0) || event.is_modified(&handle.0) {
+            if let Some(loaded) = input_maps.get(&handle.0) {
+                *input_map = loaded.clone();
+            }
+        }
+    }
+}
+
+/// assign_input_schemes reassigns the [`InputScheme`] of every controlled entity, in spawn
+/// order, when one is added or removed or a gamepad is connected or disconnected
+fn assign_input_schemes(
+    mut connection_events: EventReader<GamepadConnectionEvent>,
+    mut removed: RemovedComponents<InputScheme>,
+    gamepads: Res<Gamepads>,
+    mut query: Query<(Entity, &mut InputScheme)>,
+) {
+    let mut schemes: Vec<_> = query.iter_mut().collect();
+    let connections_changed = connection_events.read().count() > 0;
+    let entities_changed = removed.read().count() > 0
+        || schemes
+            .iter()
+            .any(|(_, scheme)| scheme.is_added());
+    if !connections_changed && !entities_changed {
+        return;
+    }
+
+    schemes.sort_by_key(|(entity, _)| *entity);
+    let current: Vec<InputScheme> = schemes
+        .iter()
+        .map(|(_, scheme)| **scheme)
+        .collect();
+    for ((_, scheme), assigned) in schemes
+        .iter_mut()
+        .zip(InputScheme::assign(&current, &gamepads))
+    {
+        scheme.set_if_neq(assigned);
+    }
+}
+
+/// update_action_state reads the value of every [`InputAction`] of each entity from the
+/// devices of its [`InputScheme`], using its own [`InputMap`] if it has one
+fn update_action_state(
+    input_map: Res<InputMap>,
+    mut query: Query<(&InputScheme, Option<&InputMap>, &mut ActionState)>,
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    buttons: Res<ButtonInput<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+) {
+    for (scheme, own_input_map, mut action_state) in query.iter_mut() {
+        let input_map = own_input_map.unwrap_or(&input_map);
+        let action_state = &mut *action_state;
+        action_state.previous = std::mem::take(&mut action_state.values);
+        for action in InputAction::ALL {
+            let value = input_map.value(action, *scheme, &keys, &gamepads, &buttons, &axes);
+            action_state.values.insert(action, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::input::{
+        gamepad::{GamepadConnection, GamepadConnectionEvent, GamepadInfo},
+        InputPlugin,
+    };
+
+    use super::*;
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            InputPlugin,
+            InputMapPlugin,
+        ));
+        app
+    }
+
+    fn connect(app: &mut App, id: usize) -> Gamepad {
+        let gamepad = Gamepad::new(id);
+        app.world_mut()
+            .send_event(GamepadConnectionEvent::new(
+                gamepad,
+                GamepadConnection::Connected(GamepadInfo {
+                    name: format!("Gamepad {}", id),
+                }),
+            ));
+        app.update();
+        gamepad
+    }
+
+    #[test]
+    fn bind_and_unbind() {
+        let jump = InputBinding::Key(KeyCode::Space);
+        let mut input_map = InputMap::new()
+            .with_binding(InputAction::Jump, jump)
+            .with_binding(InputAction::Jump, jump);
+        assert_eq!(input_map.bindings(InputAction::Jump), [jump]);
+
+        input_map.unbind(InputAction::Jump, jump);
+        assert!(input_map.bindings(InputAction::Jump).is_empty());
+        input_map.bind(InputAction::Jump, jump);
+        input_map.clear(InputAction::Jump);
+        assert!(input_map.bindings(InputAction::Jump).is_empty());
+    }
+
+    #[test]
+    fn rebind_keeps_other_bindings() {
+        let mut input_map = InputMap::default();
+        input_map.rebind(
+            InputAction::Move,
+            InputBinding::Key(KeyCode::KeyD),
+            InputBinding::Key(KeyCode::KeyW),
+        );
+        assert_eq!(
+            input_map.bindings(InputAction::Move),
+            [
+                InputBinding::NegativeKey(KeyCode::KeyA),
+                InputBinding::Key(KeyCode::KeyW),
+                InputBinding::NegativeKey(KeyCode::ArrowLeft),
+                InputBinding::Key(KeyCode::ArrowRight),
+                InputBinding::GamepadAxis(GamepadAxisType::LeftStickX),
+            ]
+        );
+
+        // Rebinding a missing binding adds the new one, without duplicating it
+        input_map.rebind(
+            InputAction::Dash,
+            InputBinding::Key(KeyCode::KeyQ),
+            InputBinding::Key(KeyCode::ShiftLeft),
+        );
+        assert_eq!(
+            input_map.bindings(InputAction::Dash),
+            [
+                InputBinding::GamepadButton(GamepadButtonType::RightTrigger),
+                InputBinding::Key(KeyCode::ShiftLeft),
+            ]
+        );
+    }
+
+    #[test]
+    fn deadzones() {
+        let mut input_map = InputMap::new().with_deadzone(InputAction::Move, -0.3);
+        assert_eq!(input_map.deadzone(InputAction::Move), 0.3);
+        assert_eq!(input_map.deadzone(InputAction::Jump), DEFAULT_DEADZONE);
+        input_map.set_deadzone(InputAction::Move, 0.5);
+        input_map.bind(
+            InputAction::Move,
+            InputBinding::GamepadAxis(GamepadAxisType::LeftStickX),
+        );
+
+        let mut app = app();
+        let gamepad = connect(&mut app, 0);
+        let player = app
+            .world_mut()
+            .spawn((PlayerInputBundle::default(), input_map))
+            .id();
+        let stick = GamepadAxis::new(gamepad, GamepadAxisType::LeftStickX);
+        for (axis, expected) in [(0.4, 0.0), (-0.4, 0.0), (0.6, 0.6), (-0.6, -0.6)] {
+            app.world_mut()
+                .resource_mut::<Axis<GamepadAxis>>()
+                .set(stick, axis);
+            app.update();
+            let action_state = app.world().get::<ActionState>(player).unwrap();
+            assert_eq!(action_state.value(InputAction::Move), expected);
+        }
+    }
+
+    #[test]
+    fn shipped_input_map_parses() {
+        let input_map: InputMap =
+            ron::de::from_str(include_str!("../../assets/controls.input.ron")).unwrap();
+        for action in InputAction::ALL {
+            assert!(
+                !input_map.bindings(action).is_empty(),
+                "{:?} is not bound",
+                action
+            );
+        }
+        assert_eq!(input_map.deadzone(InputAction::Move), 0.15);
+    }
+
+    #[test]
+    fn assign_schemes() {
+        let mut app = app();
+        let first = connect(&mut app, 0);
+        let second = connect(&mut app, 1);
+        let gamepads = app.world().resource::<Gamepads>();
+        use InputScheme::{All, Keyboard};
+
+        assert_eq!(InputScheme::assign(&[Keyboard], gamepads), vec![All]);
+        assert_eq!(
+            InputScheme::assign(&[All, All, All, All], gamepads),
+            vec![
+                Keyboard,
+                InputScheme::Gamepad(first),
+                InputScheme::Gamepad(second),
+                Keyboard
+            ]
+        );
+        // Players keep their connected device, and lose the disconnected one
+        assert_eq!(
+            InputScheme::assign(&[InputScheme::Gamepad(second), All], gamepads),
+            vec![InputScheme::Gamepad(second), Keyboard]
+        );
+        assert_eq!(
+            InputScheme::assign(&[Keyboard, InputScheme::Gamepad(Gamepad::new(2))], gamepads),
+            vec![Keyboard, InputScheme::Gamepad(first)]
+        );
+    }
+
+    #[test]
+    fn lone_player_uses_gamepad() {
+        let mut app = app();
+        let gamepad = connect(&mut app, 0);
+        let player = app
+            .world_mut()
+            .spawn(PlayerInputBundle::default())
+            .id();
+
+        app.world_mut()
+            .resource_mut::<Axis<GamepadAxis>>()
+            .set(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickX), -1.0);
+        app.update();
+
+        assert_eq!(
+            app.world().get::<InputScheme>(player),
+            Some(&InputScheme::All)
+        );
+        let action_state = app.world().get::<ActionState>(player).unwrap();
+        assert_eq!(action_state.value(InputAction::Move), -1.0);
+    }
+
+    #[test]
+    fn schemes_follow_players_and_gamepads() {
+        let mut app = app();
+        let first = app
+            .world_mut()
+            .spawn(PlayerInputBundle::default())
+            .id();
+        let second = app
+            .world_mut()
+            .spawn(PlayerInputBundle::default())
+            .id();
+        app.update();
+        let scheme = |app: &App, entity| *app.world().get::<InputScheme>(entity).unwrap();
+        assert_eq!(scheme(&app, first), InputScheme::Keyboard);
+        assert_eq!(scheme(&app, second), InputScheme::Keyboard);
+
+        // A gamepad plugged in later goes to the player sharing the keyboard
+        let gamepad = connect(&mut app, 0);
+        assert_eq!(scheme(&app, first), InputScheme::Keyboard);
+        assert_eq!(scheme(&app, second), InputScheme::Gamepad(gamepad));
+
+        app.world_mut().despawn(second);
+        app.update();
+        assert_eq!(scheme(&app, first), InputScheme::All);
+    }
+
+    #[test]
+    fn schemes_only_read_their_own_devices() {
+        let mut app = app();
+        let gamepad = connect(&mut app, 0);
+        let keyboard_player = app
+            .world_mut()
+            .spawn(PlayerInputBundle::new(InputScheme::Keyboard))
+            .id();
+        let gamepad_player = app
+            .world_mut()
+            .spawn(PlayerInputBundle::new(InputScheme::Gamepad(gamepad)))
+            .id();
+
+        app.world_mut()
+            .resource_mut::<ButtonInput<KeyCode>>()
+            .press(KeyCode::KeyD);
+        app.world_mut()
+            .resource_mut::<Axis<GamepadAxis>>()
+            .set(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickX), -1.0);
+        app.update();
+
+        let action_state = |entity| app.world().get::<ActionState>(entity).unwrap();
+        assert_eq!(action_state(keyboard_player).value(InputAction::Move), 1.0);
+        assert_eq!(action_state(gamepad_player).value(InputAction::Move), -1.0);
+    }
+}
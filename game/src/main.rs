@@ -1,6 +1,7 @@
 #![allow(unused)]
 mod components;
 mod debug;
+mod input;
 mod movement;
 mod plugins;
 
@@ -8,9 +9,11 @@ use avian2d::prelude::*;
 use bevy::prelude::*;
 use bevy_ecs_ldtk::prelude::*;
 use debug::debug_plugin::DebugPlugin;
+use input::plugin::InputMapPlugin;
 use movement::plugin::CharacterControllerPlugin;
 use plugins::health_and_damage_plugin::HealthAndDamagePlugin;
 use plugins::level_load_plugin::LevelLoadPlugin;
+use plugins::player_animation_plugin::PlayerAnimationPlugin;
 use sprite_animator::SpriteAnimationPlugin;
 
 fn main() {
@@ -18,11 +21,13 @@ fn main() {
         .add_plugins(DefaultPlugins.set(ImagePlugin::default_nearest()))
         .add_plugins(PhysicsPlugins::default().with_length_unit(24.0))
         .add_plugins(LdtkPlugin)
+        .add_plugins(InputMapPlugin)
         .add_plugins(CharacterControllerPlugin)
         //user plugins
         .add_plugins(HealthAndDamagePlugin)
         .add_plugins(LevelLoadPlugin)
+        .add_plugins(PlayerAnimationPlugin)
         .add_plugins(DebugPlugin)
-        .add_plugins(SpriteAnimationPlugin)
+        .add_plugins(SpriteAnimationPlugin::default())
         .run();
 }
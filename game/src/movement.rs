@@ -1 +1,3 @@
+pub mod abilities;
+pub mod jump;
 pub mod plugin;
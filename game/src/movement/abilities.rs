@@ -0,0 +1,376 @@
+use avian2d::{math::*, prelude::*};
+use bevy::{ecs::query::Has, prelude::*};
+
+use crate::movement::plugin::{Grounded, Landed};
+
+/// Walls are surfaces whose normal is at most this far from horizontal.
+const WALL_MAX_NORMAL_Y: Scalar = 0.5;
+
+/// The side of a character a wall is on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WallSide {
+    Left,
+    Right,
+}
+
+impl WallSide {
+    /// The direction from the character towards the wall
+    pub fn direction(self) -> Dir2 {
+        match self {
+            Self::Left => Dir2::NEG_X,
+            Self::Right => Dir2::X,
+        }
+    }
+}
+
+/// A marker component indicating that an entity is touching a wall.
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub struct TouchingWall(pub WallSide);
+
+/// Detects walls on both sides of a character controller with shape casts.
+#[derive(Component)]
+pub struct WallDetector {
+    shape: Collider,
+    distance: Scalar,
+}
+
+impl WallDetector {
+    /// Casts `shape` up to `distance` to each side of the character
+    pub fn new(shape: Collider, distance: Scalar) -> Self {
+        Self { shape, distance }
+    }
+}
+
+/// An ability allowing a character to jump again while airborne.
+/// The jumps are restored when landing.
+#[derive(Component, Clone, Copy)]
+pub struct AirJumps {
+    count: u32,
+    remaining: u32,
+}
+
+impl AirJumps {
+    pub fn new(count: u32) -> Self {
+        Self {
+            count,
+            remaining: count,
+        }
+    }
+}
+
+/// An ability allowing a character to slide down walls, falling at most at `max_fall_speed`.
+#[derive(Component, Clone, Copy)]
+pub struct WallSlide {
+    max_fall_speed: Scalar,
+}
+
+impl WallSlide {
+    pub fn new(max_fall_speed: Scalar) -> Self {
+        Self { max_fall_speed }
+    }
+}
+
+/// An ability allowing a character to jump off walls, away from the wall.
+#[derive(Component, Clone, Copy)]
+pub struct WallJump {
+    horizontal_impulse: Scalar,
+    vertical_impulse: Scalar,
+}
+
+impl WallJump {
+    pub fn new(horizontal_impulse: Scalar, vertical_impulse: Scalar) -> Self {
+        Self {
+            horizontal_impulse,
+            vertical_impulse,
+        }
+    }
+}
+
+/// An event sent to grant a traversal ability to the character controller `entity`,
+/// for example when the player unlocks it. Granting an ability again replaces it.
+#[derive(Event)]
+pub struct GrantAbility {
+    pub entity: Entity,
+    pub ability: Ability,
+}
+
+/// A traversal ability granted with [`GrantAbility`].
+#[derive(Clone, Copy)]
+pub enum Ability {
+    AirJumps(AirJumps),
+    WallSlide(WallSlide),
+    WallJump(WallJump),
+}
+
+/// Inserts the ability components granted by [`GrantAbility`] events.
+pub(crate) fn grant_abilities(
+    mut commands: Commands,
+    mut grant_event_reader: EventReader<GrantAbility>,
+) {
+    for event in grant_event_reader.read() {
+        let Some(mut entity) = commands.get_entity(event.entity) else {
+            continue;
+        };
+        match event.ability {
+            Ability::AirJumps(air_jumps) => entity.insert(air_jumps),
+            Ability::WallSlide(wall_slide) => entity.insert(wall_slide),
+            Ability::WallJump(wall_jump) => entity.insert(wall_jump),
+        };
+    }
+}
+
+/// Updates the [`TouchingWall`] status of character controllers with a [`WallDetector`].
+pub(crate) fn update_touching_wall(
+    mut commands: Commands,
+    spatial_query: SpatialQuery,
+    query: Query<(
+        Entity,
+        &WallDetector,
+        &Position,
+        &Rotation,
+        Option<&TouchingWall>,
+    )>,
+) {
+    for (entity, detector, position, rotation, touching_wall) in &query {
+        let wall_side = [WallSide::Left, WallSide::Right]
+            .into_iter()
+            .find(|side| {
+                spatial_query
+                    .cast_shape(
+                        &detector.shape,
+                        position.0,
+                        rotation.as_radians(),
+                        side.direction(),
+                        detector.distance,
+                        true,
+                        SpatialQueryFilter::from_excluded_entities([entity]),
+                    )
+                    .is_some_and(|hit| hit.normal1.y.abs() <= WALL_MAX_NORMAL_Y)
+            });
+
+        // Only touch the component when the side changes, to keep change detection meaningful
+        if wall_side == touching_wall.map(|touching_wall| touching_wall.0) {
+            continue;
+        }
+        match wall_side {
+            Some(side) => {
+                commands.entity(entity).insert(TouchingWall(side));
+            }
+            None => {
+                commands.entity(entity).remove::<TouchingWall>();
+            }
+        }
+    }
+}
+
+/// Jumps with the wall jump or air jump abilities of a character that cannot jump from the
+/// ground. Returns true if it jumped.
+pub(crate) fn ability_jump(
+    linear_velocity: &mut LinearVelocity,
+    jump_impulse: Scalar,
+    touching_wall: Option<&TouchingWall>,
+    wall_jump: Option<&WallJump>,
+    air_jumps: Option<&mut AirJumps>,
+) -> bool {
+    if let (Some(touching_wall), Some(wall_jump)) = (touching_wall, wall_jump) {
+        linear_velocity.x = -touching_wall.0.direction().x * wall_jump.horizontal_impulse;
+        linear_velocity.y = wall_jump.vertical_impulse;
+        return true;
+    }
+    if let Some(air_jumps) = air_jumps.filter(|air_jumps| air_jumps.remaining > 0) {
+        air_jumps.remaining -= 1;
+        linear_velocity.y = jump_impulse;
+        return true;
+    }
+    false
+}
+
+/// Restores the [`AirJumps`] of [`Landed`] characters.
+pub(crate) fn restore_air_jumps(mut query: Query<&mut AirJumps, With<Landed>>) {
+    for mut air_jumps in &mut query {
+        if air_jumps.remaining != air_jumps.count {
+            air_jumps.remaining = air_jumps.count;
+        }
+    }
+}
+
+/// Caps the fall speed of airborne characters with the [`WallSlide`] ability touching a wall.
+pub(crate) fn wall_slide(
+    mut query: Query<(&WallSlide, &mut LinearVelocity, Has<Grounded>), With<TouchingWall>>,
+) {
+    for (wall_slide, mut linear_velocity, is_grounded) in &mut query {
+        if !is_grounded && linear_velocity.y < -wall_slide.max_fall_speed {
+            linear_velocity.y = -wall_slide.max_fall_speed;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grant_ability_inserts_it() {
+        let mut app = App::new();
+        app.add_event::<GrantAbility>()
+            .add_systems(Update, grant_abilities);
+        let entity = app.world_mut().spawn_empty().id();
+
+        app.world_mut().send_event(GrantAbility {
+            entity,
+            ability: Ability::AirJumps(AirJumps::new(2)),
+        });
+        app.world_mut().send_event(GrantAbility {
+            entity,
+            ability: Ability::WallSlide(WallSlide::new(40.0)),
+        });
+        app.update();
+
+        let air_jumps = app.world().get::<AirJumps>(entity).unwrap();
+        assert_eq!((air_jumps.count, air_jumps.remaining), (2, 2));
+        assert!(app.world().get::<WallSlide>(entity).is_some());
+        assert!(app.world().get::<WallJump>(entity).is_none());
+    }
+
+    #[test]
+    fn air_jumps_restore_once_landed() {
+        let mut app = App::new();
+        app.add_systems(Update, restore_air_jumps);
+        let entity = app
+            .world_mut()
+            .spawn((
+                AirJumps {
+                    count: 1,
+                    remaining: 0,
+                },
+                Grounded,
+            ))
+            .id();
+
+        // Still grounded right after taking off, which is not landed
+        app.update();
+        assert_eq!(
+            app.world()
+                .get::<AirJumps>(entity)
+                .unwrap()
+                .remaining,
+            0
+        );
+
+        app.world_mut().entity_mut(entity).insert(Landed);
+        app.update();
+        assert_eq!(
+            app.world()
+                .get::<AirJumps>(entity)
+                .unwrap()
+                .remaining,
+            1
+        );
+    }
+
+    #[test]
+    fn wall_jump_pushes_away_from_wall() {
+        let wall_jump = WallJump::new(3.0, 4.0);
+        for (side, velocity_x) in [(WallSide::Left, 3.0), (WallSide::Right, -3.0)] {
+            let mut linear_velocity = LinearVelocity::ZERO;
+            let jumped = ability_jump(
+                &mut linear_velocity,
+                10.0,
+                Some(&TouchingWall(side)),
+                Some(&wall_jump),
+                None,
+            );
+            assert!(jumped);
+            assert_eq!(linear_velocity.0, Vector::new(velocity_x, 4.0));
+        }
+    }
+
+    #[test]
+    fn wall_jump_takes_precedence_over_air_jumps() {
+        let mut air_jumps = AirJumps::new(1);
+        let mut linear_velocity = LinearVelocity::ZERO;
+        let jumped = ability_jump(
+            &mut linear_velocity,
+            10.0,
+            Some(&TouchingWall(WallSide::Left)),
+            Some(&WallJump::new(3.0, 4.0)),
+            Some(&mut air_jumps),
+        );
+        assert!(jumped);
+        assert_eq!(linear_velocity.y, 4.0);
+        assert_eq!(air_jumps.remaining, 1);
+
+        // Touching a wall without the wall jump ability uses the air jumps
+        let jumped = ability_jump(
+            &mut linear_velocity,
+            10.0,
+            Some(&TouchingWall(WallSide::Left)),
+            None,
+            Some(&mut air_jumps),
+        );
+        assert!(jumped);
+        assert_eq!(linear_velocity.y, 10.0);
+        assert_eq!(air_jumps.remaining, 0);
+    }
+
+    #[test]
+    fn air_jumps_are_consumed() {
+        let mut air_jumps = AirJumps::new(2);
+        let mut linear_velocity = LinearVelocity(Vector::new(1.0, -5.0));
+        for remaining in [1, 0] {
+            assert!(ability_jump(
+                &mut linear_velocity,
+                10.0,
+                None,
+                None,
+                Some(&mut air_jumps)
+            ));
+            assert_eq!(linear_velocity.0, Vector::new(1.0, 10.0));
+            assert_eq!(air_jumps.remaining, remaining);
+        }
+
+        linear_velocity.y = -5.0;
+        assert!(!ability_jump(
+            &mut linear_velocity,
+            10.0,
+            None,
+            None,
+            Some(&mut air_jumps)
+        ));
+        assert_eq!(linear_velocity.y, -5.0);
+        assert!(!ability_jump(&mut linear_velocity, 10.0, None, None, None));
+    }
+
+    #[test]
+    fn wall_slide_caps_fall_speed() {
+        let mut app = App::new();
+        app.add_systems(Update, wall_slide);
+        let mut spawn = |touching_wall: bool, grounded: bool| {
+            let mut entity = app
+                .world_mut()
+                .spawn((WallSlide::new(2.0), LinearVelocity(Vector::new(0.0, -5.0))));
+            if touching_wall {
+                entity.insert(TouchingWall(WallSide::Right));
+            }
+            if grounded {
+                entity.insert(Grounded);
+            }
+            entity.id()
+        };
+        let sliding = spawn(true, false);
+        let away_from_wall = spawn(false, false);
+        let grounded = spawn(true, true);
+        app.update();
+
+        let velocity_y = |entity| {
+            app.world()
+                .get::<LinearVelocity>(entity)
+                .unwrap()
+                .y
+        };
+        assert_eq!(velocity_y(sliding), -2.0);
+        assert_eq!(velocity_y(away_from_wall), -5.0);
+        assert_eq!(velocity_y(grounded), -5.0);
+    }
+}
@@ -0,0 +1,368 @@
+use avian2d::{math::*, prelude::*};
+use bevy::{ecs::query::Has, prelude::*};
+
+use crate::movement::abilities::{self, AirJumps, TouchingWall, WallJump};
+use crate::movement::plugin::{JumpImpulse, Jumping, Landed, MovementAction, MovementActionKind};
+
+/// The factor applied to the upward velocity when the jump input is released early,
+/// so that the jump height depends on how long the input is held. 1.0 disables the cut.
+#[derive(Component)]
+pub struct JumpCut(Scalar);
+
+/// How long, in seconds, a character can still jump after walking off a ledge.
+/// Only landing refills it, so it does not outlast the takeoff of a jump.
+#[derive(Component)]
+pub struct CoyoteTime {
+    window: Scalar,
+    remaining: Scalar,
+}
+
+/// How long, in seconds, a jump input is remembered when the character cannot jump yet,
+/// so that pressing jump right before landing still jumps.
+/// Releasing the input before the buffered jump happens cuts that jump once it does.
+#[derive(Component)]
+pub struct JumpBuffer {
+    window: Scalar,
+    remaining: Option<Scalar>,
+    released: bool,
+}
+
+/// The factor applied to gravity while the character falls, for snappier jumps.
+/// 1.0 keeps the regular gravity.
+#[derive(Component)]
+pub struct FallGravityMultiplier(Scalar);
+
+/// A bundle that contains components for tuning jumps.
+#[derive(Bundle)]
+pub struct JumpBundle {
+    cut: JumpCut,
+    coyote_time: CoyoteTime,
+    buffer: JumpBuffer,
+    fall_gravity: FallGravityMultiplier,
+}
+
+impl JumpBundle {
+    pub const fn new(
+        cut: Scalar,
+        coyote_time: Scalar,
+        buffer: Scalar,
+        fall_gravity_multiplier: Scalar,
+    ) -> Self {
+        Self {
+            cut: JumpCut(cut),
+            coyote_time: CoyoteTime {
+                window: coyote_time,
+                remaining: 0.0,
+            },
+            buffer: JumpBuffer {
+                window: buffer,
+                remaining: None,
+                released: false,
+            },
+            fall_gravity: FallGravityMultiplier(fall_gravity_multiplier),
+        }
+    }
+}
+
+impl Default for JumpBundle {
+    /// Jumps only on the frame the input is pressed while grounded, without any cut
+    fn default() -> Self {
+        Self::new(1.0, 0.0, 0.0, 1.0)
+    }
+}
+
+/// Responds to jump [`MovementAction`] events: buffers jump inputs, jumps whenever a buffered
+/// input meets the ground or the coyote time, and cuts jumps released early.
+/// Characters that jump are marked [`Jumping`] until they leave the ground.
+/// Characters that cannot jump from the ground use their wall jump or air jump abilities.
+#[allow(clippy::type_complexity)]
+pub(crate) fn jump(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut movement_event_reader: EventReader<MovementAction>,
+    mut controllers: Query<(
+        Entity,
+        &JumpImpulse,
+        &JumpCut,
+        &mut CoyoteTime,
+        &mut JumpBuffer,
+        &mut LinearVelocity,
+        Has<Landed>,
+        Option<&TouchingWall>,
+        Option<&WallJump>,
+        Option<&mut AirJumps>,
+    )>,
+) {
+    let delta_time = time.delta_seconds_f64().adjust_precision();
+
+    for event in movement_event_reader.read() {
+        let Ok((_, _, cut, _, mut buffer, mut linear_velocity, ..)) =
+            controllers.get_mut(event.entity)
+        else {
+            continue;
+        };
+        match event.kind {
+            MovementActionKind::Jump => {
+                buffer.remaining = Some(buffer.window);
+                buffer.released = false;
+            }
+            MovementActionKind::JumpReleased => {
+                if buffer.remaining.is_some() {
+                    buffer.released = true;
+                } else if linear_velocity.y > 0.0 {
+                    linear_velocity.y *= cut.0;
+                }
+            }
+            MovementActionKind::Move(_) => {}
+        }
+    }
+
+    for (
+        entity,
+        jump_impulse,
+        cut,
+        mut coyote_time,
+        mut buffer,
+        mut linear_velocity,
+        is_landed,
+        touching_wall,
+        wall_jump,
+        mut air_jumps,
+    ) in &mut controllers
+    {
+        if is_landed {
+            coyote_time.remaining = coyote_time.window;
+        }
+
+        if buffer.remaining.is_some() {
+            let jumped = if is_landed || coyote_time.remaining > 0.0 {
+                linear_velocity.y = jump_impulse.0;
+                true
+            } else {
+                abilities::ability_jump(
+                    &mut linear_velocity,
+                    jump_impulse.0,
+                    touching_wall,
+                    wall_jump,
+                    air_jumps.as_deref_mut(),
+                )
+            };
+            if jumped {
+                if buffer.released && linear_velocity.y > 0.0 {
+                    linear_velocity.y *= cut.0;
+                }
+                buffer.remaining = None;
+                coyote_time.remaining = 0.0;
+                commands.entity(entity).insert(Jumping);
+            }
+        }
+
+        buffer.remaining = buffer
+            .remaining
+            .map(|remaining| remaining - delta_time)
+            .filter(|remaining| *remaining >= 0.0);
+        if !is_landed {
+            coyote_time.remaining -= delta_time;
+        }
+    }
+}
+
+/// Applies the extra gravity of [`FallGravityMultiplier`] to falling characters.
+pub(crate) fn apply_fall_gravity(
+    time: Res<Time>,
+    gravity: Res<Gravity>,
+    mut query: Query<(
+        &FallGravityMultiplier,
+        &mut LinearVelocity,
+        Option<&GravityScale>,
+    )>,
+) {
+    let delta_time = time.delta_seconds_f64().adjust_precision();
+
+    for (multiplier, mut linear_velocity, gravity_scale) in &mut query {
+        if linear_velocity.y < 0.0 {
+            let scale = gravity_scale.map_or(1.0, |gravity_scale| gravity_scale.0);
+            linear_velocity.y += gravity.0.y * scale * (multiplier.0 - 1.0) * delta_time;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::time::TimeUpdateStrategy;
+
+    use super::*;
+    use crate::movement::plugin::JumpImpulse;
+
+    const IMPULSE: Scalar = 10.0;
+
+    /// Each update advances the time by 0.1 seconds, and the windows last 0.15 seconds
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+                100,
+            )))
+            .add_event::<MovementAction>()
+            .add_systems(Update, jump);
+        // The first update does not advance the time
+        app.update();
+        app
+    }
+
+    fn spawn(app: &mut App, velocity_y: Scalar) -> Entity {
+        app.world_mut()
+            .spawn((
+                JumpImpulse(IMPULSE),
+                JumpBundle::new(0.5, 0.15, 0.15, 1.0),
+                LinearVelocity(Vector::new(0.0, velocity_y)),
+            ))
+            .id()
+    }
+
+    fn send(app: &mut App, entity: Entity, kind: MovementActionKind) {
+        app.world_mut()
+            .send_event(MovementAction { entity, kind });
+    }
+
+    fn velocity_y(app: &App, entity: Entity) -> Scalar {
+        app.world()
+            .get::<LinearVelocity>(entity)
+            .unwrap()
+            .y
+    }
+
+    #[test]
+    fn buffered_jump_lands_within_window() {
+        let mut app = app();
+        let entity = spawn(&mut app, -1.0);
+
+        send(&mut app, entity, MovementActionKind::Jump);
+        app.update();
+        assert_eq!(velocity_y(&app, entity), -1.0);
+
+        app.world_mut().entity_mut(entity).insert(Landed);
+        app.update();
+        assert_eq!(velocity_y(&app, entity), IMPULSE);
+    }
+
+    #[test]
+    fn buffered_jump_expires() {
+        let mut app = app();
+        let entity = spawn(&mut app, -1.0);
+
+        send(&mut app, entity, MovementActionKind::Jump);
+        app.update();
+        app.update();
+        app.world_mut().entity_mut(entity).insert(Landed);
+        app.update();
+        assert_eq!(velocity_y(&app, entity), -1.0);
+    }
+
+    #[test]
+    fn coyote_time_jumps_after_leaving_ground() {
+        let mut app = app();
+        let entity = spawn(&mut app, 0.0);
+        app.world_mut().entity_mut(entity).insert(Landed);
+        app.update();
+
+        app.world_mut()
+            .entity_mut(entity)
+            .remove::<Landed>();
+        app.world_mut()
+            .get_mut::<LinearVelocity>(entity)
+            .unwrap()
+            .y = -1.0;
+        app.update();
+        send(&mut app, entity, MovementActionKind::Jump);
+        app.update();
+        assert_eq!(velocity_y(&app, entity), IMPULSE);
+    }
+
+    #[test]
+    fn coyote_time_expires() {
+        let mut app = app();
+        let entity = spawn(&mut app, 0.0);
+        app.world_mut().entity_mut(entity).insert(Landed);
+        app.update();
+
+        app.world_mut()
+            .entity_mut(entity)
+            .remove::<Landed>();
+        app.world_mut()
+            .get_mut::<LinearVelocity>(entity)
+            .unwrap()
+            .y = -1.0;
+        app.update();
+        app.update();
+        send(&mut app, entity, MovementActionKind::Jump);
+        app.update();
+        assert_eq!(velocity_y(&app, entity), -1.0);
+    }
+
+    #[test]
+    fn jumping_does_not_refill_coyote_time() {
+        let mut app = app();
+        let entity = spawn(&mut app, 0.0);
+        app.world_mut().entity_mut(entity).insert(Landed);
+        send(&mut app, entity, MovementActionKind::Jump);
+        app.update();
+        assert_eq!(velocity_y(&app, entity), IMPULSE);
+        assert!(app.world().get::<Jumping>(entity).is_some());
+
+        // Still grounded right after taking off, which is not landed
+        app.world_mut()
+            .entity_mut(entity)
+            .remove::<Landed>();
+        app.world_mut()
+            .get_mut::<LinearVelocity>(entity)
+            .unwrap()
+            .y = 5.0;
+        send(&mut app, entity, MovementActionKind::Jump);
+        app.update();
+        assert_eq!(velocity_y(&app, entity), 5.0);
+    }
+
+    #[test]
+    fn jumps_while_walking_uphill() {
+        let mut app = app();
+        let entity = spawn(&mut app, 2.0);
+        app.world_mut().entity_mut(entity).insert(Landed);
+        for _ in 0..5 {
+            app.update();
+        }
+
+        send(&mut app, entity, MovementActionKind::Jump);
+        app.update();
+        assert_eq!(velocity_y(&app, entity), IMPULSE);
+    }
+
+    #[test]
+    fn releasing_jump_cuts_it() {
+        let mut app = app();
+        let entity = spawn(&mut app, 0.0);
+        app.world_mut().entity_mut(entity).insert(Landed);
+        send(&mut app, entity, MovementActionKind::Jump);
+        app.update();
+
+        send(&mut app, entity, MovementActionKind::JumpReleased);
+        app.update();
+        assert_eq!(velocity_y(&app, entity), IMPULSE * 0.5);
+    }
+
+    #[test]
+    fn releasing_buffered_jump_cuts_it_once_it_jumps() {
+        let mut app = app();
+        let entity = spawn(&mut app, -1.0);
+        send(&mut app, entity, MovementActionKind::Jump);
+        send(&mut app, entity, MovementActionKind::JumpReleased);
+        app.update();
+        assert_eq!(velocity_y(&app, entity), -1.0);
+
+        app.world_mut().entity_mut(entity).insert(Landed);
+        app.update();
+        assert_eq!(velocity_y(&app, entity), IMPULSE * 0.5);
+    }
+}
@@ -1,31 +1,61 @@
 #![allow(dead_code)]
 // Taken from avian2d dynamic character controller example
 use avian2d::{math::*, prelude::*};
-use bevy::{ecs::query::Has, prelude::*};
+use bevy::prelude::*;
+use sprite_animator::{Facing, SpriteAnimSet};
+
+use crate::input::plugin::{ActionState, InputAction};
+use crate::movement::abilities::{self, GrantAbility, WallDetector};
+use crate::movement::jump::{self, JumpBundle};
+
+/// How far walls are detected from the sides of character controllers.
+const WALL_DETECTION_DISTANCE: Scalar = 2.0;
 
 pub struct CharacterControllerPlugin;
 
 impl Plugin for CharacterControllerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<MovementAction>().add_systems(
-            Update,
-            (
-                keyboard_input,
-                gamepad_input,
-                update_grounded,
-                movement,
-                apply_movement_damping,
-            )
-                .chain(),
-        );
+        app.add_event::<MovementAction>()
+            .add_event::<GrantAbility>()
+            .add_systems(
+                Update,
+                (
+                    action_input,
+                    abilities::grant_abilities,
+                    update_grounded,
+                    abilities::update_touching_wall,
+                    abilities::restore_air_jumps,
+                    movement,
+                    jump::jump,
+                    jump::apply_fall_gravity,
+                    abilities::wall_slide,
+                    apply_movement_damping,
+                )
+                    .chain()
+                    .in_set(CharacterControllerSet)
+                    .before(SpriteAnimSet::Prepare),
+            );
     }
 }
 
-/// An event sent for a movement input action.
+/// System set of the character controller systems, from reading the input to moving the
+/// characters. Systems reacting to the movement of characters should run after it.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CharacterControllerSet;
+
+/// An event sent for a movement input action of the character controller `entity`.
 #[derive(Event)]
-pub enum MovementAction {
+pub struct MovementAction {
+    pub entity: Entity,
+    pub kind: MovementActionKind,
+}
+
+/// The kind of a [`MovementAction`].
+pub enum MovementActionKind {
     Move(Scalar),
     Jump,
+    /// The jump input was released, which cuts the jump short
+    JumpReleased,
 }
 
 /// A marker component indicating that an entity is using a character controller.
@@ -36,6 +66,19 @@ pub struct CharacterController;
 #[derive(Component)]
 #[component(storage = "SparseSet")]
 pub struct Grounded;
+
+/// A marker component indicating that an entity jumped and has not left the ground yet,
+/// as the ground caster still hits the ground for a few frames after taking off.
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub struct Jumping;
+
+/// A marker component indicating that an entity is on the ground without taking off from a
+/// jump. Coyote time and air jumps are only restored once landed.
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub struct Landed;
+
 /// The acceleration used for character movement.
 #[derive(Component)]
 pub struct MovementAcceleration(Scalar);
@@ -46,7 +89,7 @@ pub struct MovementDampingFactor(Scalar);
 
 /// The strength of a jump.
 #[derive(Component)]
-pub struct JumpImpulse(Scalar);
+pub struct JumpImpulse(pub(crate) Scalar);
 
 /// The maximum angle a slope can have for a character controller
 /// to be able to climb and jump. If the slope is steeper than this angle,
@@ -62,8 +105,10 @@ pub struct CharacterControllerBundle {
     rigid_body: RigidBody,
     collider: Collider,
     ground_caster: ShapeCaster,
+    wall_detector: WallDetector,
     locked_axes: LockedAxes,
     movement: MovementBundle,
+    jump: JumpBundle,
 }
 
 /// A bundle that contains components for character movement.
@@ -107,10 +152,12 @@ impl CharacterControllerBundle {
             character_controller: CharacterController,
             rigid_body: RigidBody::Dynamic,
             collider,
-            ground_caster: ShapeCaster::new(caster_shape, Vector::ZERO, 0.0, Dir2::NEG_Y)
+            ground_caster: ShapeCaster::new(caster_shape.clone(), Vector::ZERO, 0.0, Dir2::NEG_Y)
                 .with_max_time_of_impact(10.0),
+            wall_detector: WallDetector::new(caster_shape, WALL_DETECTION_DISTANCE),
             locked_axes: LockedAxes::ROTATION_LOCKED,
             movement: MovementBundle::default(),
+            jump: JumpBundle::default(),
         }
     }
 
@@ -124,65 +171,68 @@ impl CharacterControllerBundle {
         self.movement = MovementBundle::new(acceleration, damping, jump_impulse, max_slope_angle);
         self
     }
-}
-
-/// Sends [`MovementAction`] events based on keyboard input.
-fn keyboard_input(
-    mut movement_event_writer: EventWriter<MovementAction>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
-) {
-    let left = keyboard_input.any_pressed([KeyCode::KeyA, KeyCode::ArrowLeft]);
-    let right = keyboard_input.any_pressed([KeyCode::KeyD, KeyCode::ArrowRight]);
-
-    let horizontal = right as i8 - left as i8;
-    let direction = horizontal as Scalar;
-
-    if direction != 0.0 {
-        movement_event_writer.send(MovementAction::Move(direction));
-    }
 
-    if keyboard_input.just_pressed(KeyCode::Space) {
-        movement_event_writer.send(MovementAction::Jump);
+    pub fn with_jump(
+        mut self,
+        cut: Scalar,
+        coyote_time: Scalar,
+        buffer: Scalar,
+        fall_gravity_multiplier: Scalar,
+    ) -> Self {
+        self.jump = JumpBundle::new(cut, coyote_time, buffer, fall_gravity_multiplier);
+        self
     }
 }
 
-/// Sends [`MovementAction`] events based on gamepad input.
-fn gamepad_input(
+/// Sends [`MovementAction`] events based on the [`ActionState`] of each character controller,
+/// so that each player only moves their own character.
+fn action_input(
     mut movement_event_writer: EventWriter<MovementAction>,
-    gamepads: Res<Gamepads>,
-    axes: Res<Axis<GamepadAxis>>,
-    buttons: Res<ButtonInput<GamepadButton>>,
+    query: Query<(Entity, &ActionState), With<CharacterController>>,
 ) {
-    for gamepad in gamepads.iter() {
-        let axis_lx = GamepadAxis {
-            gamepad,
-            axis_type: GamepadAxisType::LeftStickX,
-        };
-
-        if let Some(x) = axes.get(axis_lx) {
-            movement_event_writer.send(MovementAction::Move(x as Scalar));
+    for (entity, action_state) in &query {
+        let direction = action_state.value(InputAction::Move) as Scalar;
+        if direction != 0.0 {
+            movement_event_writer.send(MovementAction {
+                entity,
+                kind: MovementActionKind::Move(direction),
+            });
         }
 
-        let jump_button = GamepadButton {
-            gamepad,
-            button_type: GamepadButtonType::South,
-        };
+        if action_state.just_pressed(InputAction::Jump) {
+            movement_event_writer.send(MovementAction {
+                entity,
+                kind: MovementActionKind::Jump,
+            });
+        }
 
-        if buttons.just_pressed(jump_button) {
-            movement_event_writer.send(MovementAction::Jump);
+        if action_state.just_released(InputAction::Jump) {
+            movement_event_writer.send(MovementAction {
+                entity,
+                kind: MovementActionKind::JumpReleased,
+            });
         }
     }
 }
 
-/// Updates the [`Grounded`] status for character controllers.
+/// Updates the [`Grounded`] and [`Landed`] status for character controllers.
+/// Leaving the ground ends the [`Jumping`] status.
+#[allow(clippy::type_complexity)]
 fn update_grounded(
     mut commands: Commands,
     mut query: Query<
-        (Entity, &ShapeHits, &Rotation, Option<&MaxSlopeAngle>),
+        (
+            Entity,
+            &ShapeHits,
+            &Rotation,
+            Option<&MaxSlopeAngle>,
+            Has<Jumping>,
+            Has<Landed>,
+        ),
         With<CharacterController>,
     >,
 ) {
-    for (entity, hits, rotation, max_slope_angle) in &mut query {
+    for (entity, hits, rotation, max_slope_angle, is_jumping, was_landed) in &mut query {
         // The character is grounded if the shape caster has a hit with a normal
         // that isn't too steep.
         let is_grounded = hits.iter().any(|hit| {
@@ -199,20 +249,30 @@ fn update_grounded(
         if is_grounded {
             commands.entity(entity).insert(Grounded);
         } else {
-            commands.entity(entity).remove::<Grounded>();
+            commands
+                .entity(entity)
+                .remove::<(Grounded, Jumping)>();
+        }
+
+        let is_landed = is_grounded && !is_jumping;
+        if is_landed && !was_landed {
+            commands.entity(entity).insert(Landed);
+        } else if !is_landed && was_landed {
+            commands.entity(entity).remove::<Landed>();
         }
     }
 }
 
-/// Responds to [`MovementAction`] events and moves character controllers accordingly.
+/// Responds to move [`MovementAction`] events and moves their character controller accordingly.
+/// Jumps are handled by [`jump::jump`].
+/// Also turns the [`Facing`] of animated characters towards the direction they move in.
 fn movement(
     time: Res<Time>,
     mut movement_event_reader: EventReader<MovementAction>,
     mut controllers: Query<(
         &MovementAcceleration,
-        &JumpImpulse,
         &mut LinearVelocity,
-        Has<Grounded>,
+        Option<&mut Facing>,
     )>,
 ) {
     // Precision is adjusted so that the example works with
@@ -220,19 +280,19 @@ fn movement(
     let delta_time = time.delta_seconds_f64().adjust_precision();
 
     for event in movement_event_reader.read() {
-        for (movement_acceleration, jump_impulse, mut linear_velocity, is_grounded) in
-            &mut controllers
+        let MovementActionKind::Move(direction) = event.kind else {
+            continue;
+        };
+        let Ok((movement_acceleration, mut linear_velocity, facing)) =
+            controllers.get_mut(event.entity)
+        else {
+            continue;
+        };
+        linear_velocity.x += direction * movement_acceleration.0 * delta_time;
+        if let (Some(mut facing), Some(new_facing)) =
+            (facing, Facing::from_direction(direction as f32))
         {
-            match event {
-                MovementAction::Move(direction) => {
-                    linear_velocity.x += *direction * movement_acceleration.0 * delta_time;
-                }
-                MovementAction::Jump => {
-                    if is_grounded {
-                        linear_velocity.y = jump_impulse.0;
-                    }
-                }
-            }
+            facing.set_if_neq(new_facing);
         }
     }
 }
@@ -244,3 +304,62 @@ fn apply_movement_damping(mut query: Query<(&MovementDampingFactor, &mut LinearV
         linear_velocity.x *= damping_factor.0;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::input::{
+        gamepad::{GamepadConnection, GamepadConnectionEvent, GamepadInfo},
+        InputPlugin,
+    };
+
+    use super::*;
+    use crate::input::plugin::{InputMapPlugin, InputScheme, PlayerInputBundle};
+
+    #[test]
+    fn actions_only_reach_their_scheme() {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            InputPlugin,
+            InputMapPlugin,
+        ))
+        .add_event::<MovementAction>()
+        .add_systems(Update, action_input);
+
+        let gamepad = Gamepad::new(0);
+        app.world_mut()
+            .send_event(GamepadConnectionEvent::new(
+                gamepad,
+                GamepadConnection::Connected(GamepadInfo {
+                    name: "Gamepad 0".to_string(),
+                }),
+            ));
+        app.update();
+
+        let keyboard_player = app
+            .world_mut()
+            .spawn((
+                CharacterController,
+                PlayerInputBundle::new(InputScheme::Keyboard),
+            ))
+            .id();
+        app.world_mut().spawn((
+            CharacterController,
+            PlayerInputBundle::new(InputScheme::Gamepad(gamepad)),
+        ));
+
+        app.world_mut()
+            .resource_mut::<ButtonInput<KeyCode>>()
+            .press(KeyCode::KeyD);
+        app.update();
+
+        let events = app.world().resource::<Events<MovementAction>>();
+        let mut reader = events.get_reader();
+        let entities: Vec<Entity> = reader
+            .read(events)
+            .map(|event| event.entity)
+            .collect();
+        assert_eq!(entities, vec![keyboard_player]);
+    }
+}
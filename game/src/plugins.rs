@@ -1,2 +1,3 @@
 pub mod health_and_damage_plugin;
 pub mod level_load_plugin;
+pub mod player_animation_plugin;
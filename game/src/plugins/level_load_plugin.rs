@@ -3,13 +3,16 @@ use crate::components::{
     health::{Health, HealthBundle},
     player::{Player, PlayerBundle},
 };
+use crate::input::plugin::PlayerInputBundle;
 use crate::movement::plugin::*;
 use avian2d::math::{Scalar, Vector};
 use avian2d::prelude::*;
 use bevy::prelude::*;
 use bevy_ecs_ldtk::prelude::*;
+use sprite_animator::{Facing, SpriteAnimSheet};
 
 const LDTK_PROJECT_NAME: &str = "TestLevel.ldtk";
+const PLAYER_ANIM_SHEET: &str = "oak_woods/character/char_blue.anim.ron";
 const PLAYER_COLLIDER_RADIUS: Scalar = 12.5;
 const PLAYER_COLLIDER_LOWER_ENDPOINT: Vector = Vector::new(0.0, -15.0);
 const PLAYER_COLLIDER_UPPER_ENDPOINT: Vector = Vector::new(0.0, -8.0);
@@ -17,6 +20,10 @@ const PLAYER_ACCELERATION: Scalar = 125.0;
 const PLAYER_STARTING_HEALTH: u32 = 100;
 const PLAYER_DAMPING: Scalar = 1.0;
 const PLAYER_JUMP_IMPULSE: Scalar = 130.0;
+const PLAYER_JUMP_CUT: Scalar = 0.5;
+const PLAYER_COYOTE_TIME: Scalar = 0.1;
+const PLAYER_JUMP_BUFFER: Scalar = 0.1;
+const PLAYER_FALL_GRAVITY_MULTIPLIER: Scalar = 1.5;
 const PLAYER_MAX_SLOPE_ANGLE: Scalar = 30.0 as Scalar;
 const PLAYER_COLLIDER_DENSITY: ColliderDensity = ColliderDensity(5.0);
 const PLAYER_GRAVITY_SCALE: GravityScale = GravityScale(10.0);
@@ -55,8 +62,13 @@ fn world_setup(mut command: Commands, asset_server: Res<AssetServer>) {
 }
 
 ///player_setup queries for any entities that were added the previous update
-///with the "Player" tag then adds appropriate components
-fn player_setup(mut commands: Commands, query: Query<Entity, Added<Player>>) {
+///with the "Player" tag then adds appropriate components, including its animations.
+///The input plugin narrows the input scheme of each player once several players exist
+fn player_setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    query: Query<Entity, Added<Player>>,
+) {
     let new_players = query.iter();
 
     for entity in new_players {
@@ -74,6 +86,12 @@ fn player_setup(mut commands: Commands, query: Query<Entity, Added<Player>>) {
             PLAYER_DAMPING,
             PLAYER_JUMP_IMPULSE,
             PLAYER_MAX_SLOPE_ANGLE.to_radians(),
+        )
+        .with_jump(
+            PLAYER_JUMP_CUT,
+            PLAYER_COYOTE_TIME,
+            PLAYER_JUMP_BUFFER,
+            PLAYER_FALL_GRAVITY_MULTIPLIER,
         );
 
         commands.entity(entity).insert(player_health);
@@ -90,6 +108,13 @@ fn player_setup(mut commands: Commands, query: Query<Entity, Added<Player>>) {
         commands
             .entity(entity)
             .insert(PLAYER_GRAVITY_SCALE);
+        commands.entity(entity).insert((
+            asset_server.load::<SpriteAnimSheet>(PLAYER_ANIM_SHEET),
+            Facing::default(),
+        ));
+        commands
+            .entity(entity)
+            .insert(PlayerInputBundle::default());
     }
 }
 
@@ -0,0 +1,87 @@
+use avian2d::{math::Scalar, prelude::*};
+use bevy::{
+    ecs::{entity::EntityHashSet, query::Has},
+    prelude::*,
+};
+use sprite_animator::{sprite_anim_key, SpriteAnimController, SpriteAnimError, SpriteAnimSet};
+
+use crate::components::player::Player;
+use crate::movement::plugin::{
+    CharacterControllerSet, Grounded, MovementAction, MovementActionKind,
+};
+
+///Horizontal speed above which a grounded player is considered running
+const PLAYER_RUN_THRESHOLD: Scalar = 5.0;
+
+sprite_anim_key! {
+    ///Animations of char_blue.anim.ron played by the player
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum PlayerAnim {
+        Idle,
+        Run,
+        Jump,
+        Fall,
+    }
+}
+
+///Plugin for playing the player animation matching its movement
+pub struct PlayerAnimationPlugin;
+
+impl Plugin for PlayerAnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            update_player_animation
+                .after(CharacterControllerSet)
+                .before(SpriteAnimSet::Prepare),
+        );
+    }
+}
+
+///update_player_animation plays idle/run on the ground and jump/fall in the air,
+///based on the movement actions sent to the player this update and its velocity
+fn update_player_animation(
+    mut movement_event_reader: EventReader<MovementAction>,
+    mut query: Query<
+        (
+            Entity,
+            &mut SpriteAnimController,
+            &LinearVelocity,
+            Has<Grounded>,
+        ),
+        With<Player>,
+    >,
+) {
+    let mut moving = EntityHashSet::default();
+    let mut jumping = EntityHashSet::default();
+    for event in movement_event_reader.read() {
+        match event.kind {
+            MovementActionKind::Move(direction) if direction != 0.0 => {
+                moving.insert(event.entity);
+            }
+            MovementActionKind::Jump => {
+                jumping.insert(event.entity);
+            }
+            _ => {}
+        }
+    }
+
+    for (entity, mut ac, linear_velocity, is_grounded) in query.iter_mut() {
+        let move_pressed = moving.contains(&entity);
+        let jump_pressed = jumping.contains(&entity);
+        let anim = if (jump_pressed && is_grounded) || (!is_grounded && linear_velocity.y > 0.0) {
+            PlayerAnim::Jump
+        } else if !is_grounded {
+            PlayerAnim::Fall
+        } else if move_pressed || linear_velocity.x.abs() > PLAYER_RUN_THRESHOLD {
+            PlayerAnim::Run
+        } else {
+            PlayerAnim::Idle
+        };
+        // Already playing the animation is not an error here
+        match ac.play(anim) {
+            Ok(()) | Err(SpriteAnimError::AlreadyPlaying(_)) => {}
+            Err(err) => error!("{}", err),
+        }
+    }
+}
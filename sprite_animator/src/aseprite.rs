@@ -0,0 +1,398 @@
+use core::fmt;
+use std::time::Duration;
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext, ParseAssetPathError},
+    prelude::*,
+};
+use serde::{
+    de::{MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer,
+};
+
+use crate::{SpriteAnimController, SpriteAnimData, SpriteAnimMode, SpriteAnimSheet};
+
+/// Framerate given to sheets imported from Aseprite. Every frame has its own duration, so it is
+/// only used as a fallback.
+const ASEPRITE_FPS: f32 = 10.0;
+
+/// Sprite sheet exported from Aseprite, parsed from its JSON sidecar file.
+/// Both the "Hash" and "Array" export formats are supported.
+///
+/// Every frame tag becomes a `SpriteAnimData` using the durations set in Aseprite:
+/// - `forward`: `SpriteAnimMode::Repeating`
+/// - `reverse`: `SpriteAnimMode::Repeating`, with the frames in reverse order
+/// - `pingpong`: `SpriteAnimMode::Mirror`
+/// - `pingpong_reverse`: `SpriteAnimMode::Mirror`, with the frames in reverse order
+///
+/// A sheet without any frame tag is imported as a single "Default" animation holding every frame.
+///
+/// Example usage:
+/// ```Rust
+/// let aseprite = AsepriteSheet::from_slice(&bytes)?;
+/// let layout_handle = texture_atlas_layouts.add(aseprite.layout());
+/// let controller = aseprite.controller();
+/// ```
+#[derive(Debug, Deserialize)]
+pub struct AsepriteSheet {
+    #[serde(deserialize_with = "ordered_frames")]
+    frames: Vec<AsepriteFrame>,
+    meta: AsepriteMeta,
+}
+
+#[derive(Debug, Deserialize)]
+struct AsepriteFrame {
+    frame: AsepriteRect,
+    duration: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct AsepriteRect {
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct AsepriteSize {
+    w: u32,
+    h: u32,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AsepriteMeta {
+    image: String,
+    size: AsepriteSize,
+    #[serde(default)]
+    frame_tags: Vec<AsepriteTag>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AsepriteTag {
+    name: String,
+    from: usize,
+    to: usize,
+    #[serde(default)]
+    direction: AsepriteDirection,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum AsepriteDirection {
+    #[default]
+    Forward,
+    Reverse,
+    Pingpong,
+    PingpongReverse,
+}
+
+impl AsepriteSheet {
+    /// Parses the JSON file exported by Aseprite
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(bytes)
+    }
+
+    /// Path of the sprite sheet image, relative to the JSON file
+    pub fn image(&self) -> &str {
+        &self.meta.image
+    }
+
+    /// Creates the `TextureAtlasLayout` holding every frame of the sheet
+    pub fn layout(&self) -> TextureAtlasLayout {
+        let mut layout =
+            TextureAtlasLayout::new_empty(UVec2::new(self.meta.size.w, self.meta.size.h));
+        for frame in &self.frames {
+            let rect = &frame.frame;
+            layout.add_texture(URect::new(rect.x, rect.y, rect.x + rect.w, rect.y + rect.h));
+        }
+        layout
+    }
+
+    /// Creates a `SpriteAnimData` for every frame tag of the sheet
+    pub fn animations(&self) -> Vec<SpriteAnimData> {
+        if self.meta.frame_tags.is_empty() {
+            let frames = (0..self.frames.len()).collect();
+            return vec![self.animation("Default", frames, SpriteAnimMode::Repeating)];
+        }
+        self.meta
+            .frame_tags
+            .iter()
+            .map(|tag| {
+                let frames: Vec<usize> = (tag.from..=tag.to).collect();
+                let (frames, mode) = match tag.direction {
+                    AsepriteDirection::Forward => (frames, SpriteAnimMode::Repeating),
+                    AsepriteDirection::Reverse => (
+                        frames.into_iter().rev().collect(),
+                        SpriteAnimMode::Repeating,
+                    ),
+                    AsepriteDirection::Pingpong => (frames, SpriteAnimMode::Mirror),
+                    AsepriteDirection::PingpongReverse => {
+                        (frames.into_iter().rev().collect(), SpriteAnimMode::Mirror)
+                    }
+                };
+                self.animation(&tag.name, frames, mode)
+            })
+            .collect()
+    }
+
+    /// Creates a `SpriteAnimController` holding every animation of the sheet.
+    /// The first frame tag is used as the default animation.
+    pub fn controller(&self) -> SpriteAnimController {
+        self.to_sheet(Handle::default(), Handle::default())
+            .controller()
+    }
+
+    /// Creates a `SpriteAnimSheet` using the given image and layout handles
+    fn to_sheet(
+        &self,
+        image: Handle<Image>,
+        layout: Handle<TextureAtlasLayout>,
+    ) -> SpriteAnimSheet {
+        let animations = self.animations();
+        let default = animations
+            .first()
+            .map(|anim| anim.name().to_string());
+        SpriteAnimSheet::new(image, layout, ASEPRITE_FPS, default, animations)
+    }
+
+    /// Creates a `SpriteAnimData` using the durations of the given frames.
+    /// Frames missing from the sheet fall back on `ASEPRITE_FPS`, and are reported by
+    /// `SpriteAnimData::validate` once the layout is known.
+    fn animation(&self, name: &str, frames: Vec<usize>, mode: SpriteAnimMode) -> SpriteAnimData {
+        let durations: Vec<(usize, Duration)> = frames
+            .iter()
+            .enumerate()
+            .filter_map(|(index, frame)| {
+                let frame = self.frames.get(*frame)?;
+                Some((index, Duration::from_millis(frame.duration)))
+            })
+            .collect();
+        let mut anim = SpriteAnimData::new(name, frames).with_mode(mode);
+        for (index, duration) in durations {
+            anim = anim.with_frame_duration(index, duration);
+        }
+        anim
+    }
+}
+
+/// Deserializes the frames of both the "Hash" and "Array" formats, keeping the order in which
+/// they appear in the file.
+fn ordered_frames<'de, D>(deserializer: D) -> Result<Vec<AsepriteFrame>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct FramesVisitor;
+
+    impl<'de> Visitor<'de> for FramesVisitor {
+        type Value = Vec<AsepriteFrame>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("an array or a map of Aseprite frames")
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut frames = Vec::new();
+            while let Some(frame) = seq.next_element()? {
+                frames.push(frame);
+            }
+            Ok(frames)
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+            let mut frames = Vec::new();
+            while let Some((_, frame)) = map.next_entry::<String, AsepriteFrame>()? {
+                frames.push(frame);
+            }
+            Ok(frames)
+        }
+    }
+
+    deserializer.deserialize_any(FramesVisitor)
+}
+
+#[derive(Debug)]
+pub enum AsepriteLoaderError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    Path(ParseAssetPathError),
+}
+
+impl fmt::Display for AsepriteLoaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "Could not read the Aseprite file: {}", err),
+            Self::Json(err) => write!(f, "Could not parse the Aseprite file: {}", err),
+            Self::Path(err) => write!(f, "Invalid image path: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for AsepriteLoaderError {}
+
+impl From<std::io::Error> for AsepriteLoaderError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for AsepriteLoaderError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Json(err)
+    }
+}
+
+impl From<ParseAssetPathError> for AsepriteLoaderError {
+    fn from(err: ParseAssetPathError) -> Self {
+        Self::Path(err)
+    }
+}
+
+/// Loads `SpriteAnimSheet` assets from the `.aseprite.json` files exported by Aseprite.
+///
+/// Example usage:
+/// ```Rust
+/// commands.spawn(SpriteAnimSheetBundle {
+///     sheet: asset_server.load("oak_woods/character/char_blue.aseprite.json"),
+///     ..default()
+/// });
+/// ```
+#[derive(Default)]
+pub struct AsepriteLoader;
+
+impl AssetLoader for AsepriteLoader {
+    type Asset = SpriteAnimSheet;
+    type Settings = ();
+    type Error = AsepriteLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let aseprite = AsepriteSheet::from_slice(&bytes)?;
+
+        let image_path = load_context
+            .asset_path()
+            .resolve_embed(aseprite.image())?;
+        let image = load_context.load(image_path);
+        let layout = load_context.add_labeled_asset("layout".to_string(), aseprite.layout());
+
+        Ok(aseprite.to_sheet(image, layout))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["aseprite.json"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SpriteAnimError;
+
+    const HASH: &str = r#"{
+        "frames": {
+            "char 0.aseprite": { "frame": { "x": 0, "y": 0, "w": 16, "h": 16 }, "duration": 100 },
+            "char 1.aseprite": { "frame": { "x": 16, "y": 0, "w": 16, "h": 16 }, "duration": 200 },
+            "char 2.aseprite": { "frame": { "x": 32, "y": 0, "w": 16, "h": 16 }, "duration": 300 }
+        },
+        "meta": {
+            "image": "char.png",
+            "size": { "w": 48, "h": 16 },
+            "frameTags": [
+                { "name": "Forward", "from": 0, "to": 2, "direction": "forward" },
+                { "name": "Reverse", "from": 0, "to": 2, "direction": "reverse" },
+                { "name": "Pingpong", "from": 0, "to": 1, "direction": "pingpong" },
+                { "name": "PingpongReverse", "from": 1, "to": 2, "direction": "pingpong_reverse" },
+                { "name": "Missing", "from": 2, "to": 3 }
+            ]
+        }
+    }"#;
+
+    const ARRAY: &str = r#"{
+        "frames": [
+            { "filename": "char 0.aseprite", "frame": { "x": 0, "y": 0, "w": 16, "h": 16 }, "duration": 100 },
+            { "filename": "char 1.aseprite", "frame": { "x": 0, "y": 16, "w": 16, "h": 16 }, "duration": 200 }
+        ],
+        "meta": { "image": "char.png", "size": { "w": 16, "h": 32 } }
+    }"#;
+
+    fn frames_and_mode(anim: &SpriteAnimData) -> (&[usize], &SpriteAnimMode) {
+        (anim.frames(), anim.mode())
+    }
+
+    #[test]
+    fn parse_hash() {
+        let aseprite = AsepriteSheet::from_slice(HASH.as_bytes()).unwrap();
+        assert_eq!(aseprite.image(), "char.png");
+
+        let layout = aseprite.layout();
+        assert_eq!(layout.size, UVec2::new(48, 16));
+        assert_eq!(layout.textures[2], URect::new(32, 0, 48, 16));
+
+        let controller = aseprite.controller();
+        assert_eq!(controller.current().name(), "Forward");
+        let forward = controller.library().get("Forward").unwrap();
+        assert_eq!(forward.frame_duration(1), Some(Duration::from_millis(200)));
+    }
+
+    #[test]
+    fn parse_array() {
+        let aseprite = AsepriteSheet::from_slice(ARRAY.as_bytes()).unwrap();
+        assert_eq!(aseprite.layout().textures[1], URect::new(0, 16, 16, 32));
+
+        let animations = aseprite.animations();
+        assert_eq!(animations.len(), 1);
+        assert_eq!(animations[0].name(), "Default");
+        assert_eq!(
+            frames_and_mode(&animations[0]),
+            (&[0, 1][..], &SpriteAnimMode::Repeating)
+        );
+    }
+
+    #[test]
+    fn tag_directions() {
+        let aseprite = AsepriteSheet::from_slice(HASH.as_bytes()).unwrap();
+        let animations = aseprite.animations();
+        assert_eq!(
+            frames_and_mode(&animations[0]),
+            (&[0, 1, 2][..], &SpriteAnimMode::Repeating)
+        );
+        assert_eq!(
+            frames_and_mode(&animations[1]),
+            (&[2, 1, 0][..], &SpriteAnimMode::Repeating)
+        );
+        assert_eq!(
+            animations[1].frame_duration(0),
+            Some(Duration::from_millis(300))
+        );
+        assert_eq!(
+            frames_and_mode(&animations[2]),
+            (&[0, 1][..], &SpriteAnimMode::Mirror)
+        );
+        assert_eq!(
+            frames_and_mode(&animations[3]),
+            (&[2, 1][..], &SpriteAnimMode::Mirror)
+        );
+    }
+
+    #[test]
+    fn missing_frames_use_the_default_fps() {
+        let aseprite = AsepriteSheet::from_slice(HASH.as_bytes()).unwrap();
+        let controller = aseprite.controller();
+        let missing = controller.library().get("Missing").unwrap();
+        assert_eq!(missing.frame_duration(0), Some(Duration::from_millis(300)));
+        assert_eq!(missing.frame_duration(1), None);
+
+        let layout = aseprite.layout();
+        assert!(matches!(
+            missing.validate(Some(&layout))[..],
+            [SpriteAnimError::FrameOutOfRange(..)]
+        ));
+    }
+}
@@ -0,0 +1,77 @@
+use bevy::prelude::*;
+
+use crate::{SpriteAnimController, SpriteAnimTime};
+
+/// Distance in front of the animated sprite at which the fading sprite is drawn
+const CROSSFADE_Z_OFFSET: f32 = 0.001;
+
+/// Temporary sprite displaying the last frame of the previous animation while it fades out.
+/// Spawned as a child of the animated entity, and despawned once fully transparent.
+#[derive(Component, Debug)]
+pub struct SpriteAnimCrossfade {
+    timer: Timer,
+    alpha: f32,
+}
+
+/// System that spawns a fading copy of the sprite of every `SpriteAnimController` that just
+/// started a crossfade. Must run before `play_animation` changes the displayed frame.
+#[allow(clippy::type_complexity)]
+pub(crate) fn start_crossfades(
+    mut commands: Commands,
+    mut query: Query<(
+        Entity,
+        &mut SpriteAnimController,
+        Option<(&Sprite, &Handle<Image>)>,
+        Option<&TextureAtlas>,
+    )>,
+) {
+    for (entity, mut ac, sprite, ta) in query.iter_mut() {
+        let Some(duration) = ac.pending_crossfade else {
+            continue;
+        };
+        ac.pending_crossfade = None;
+        let Some((sprite, texture)) = sprite else {
+            continue;
+        };
+        commands.entity(entity).with_children(|parent| {
+            let mut fading = parent.spawn((
+                SpriteBundle {
+                    sprite: sprite.clone(),
+                    texture: texture.clone(),
+                    transform: Transform::from_xyz(0.0, 0.0, CROSSFADE_Z_OFFSET),
+                    ..default()
+                },
+                SpriteAnimCrossfade {
+                    timer: Timer::new(duration, TimerMode::Once),
+                    alpha: sprite.color.alpha(),
+                },
+            ));
+            if let Some(ta) = ta {
+                fading.insert(ta.clone());
+            }
+        });
+    }
+}
+
+/// System that fades out every `SpriteAnimCrossfade`, and despawns those that are done.
+/// Fading sprites keep flipping along with their animated sprite, such as when its `Facing`
+/// changes during the crossfade.
+pub(crate) fn update_crossfades(
+    mut commands: Commands,
+    mut query: Query<(Entity, &Parent, &mut SpriteAnimCrossfade, &mut Sprite)>,
+    parents: Query<&Sprite, Without<SpriteAnimCrossfade>>,
+    time: SpriteAnimTime,
+) {
+    for (entity, parent, mut crossfade, mut sprite) in query.iter_mut() {
+        crossfade.timer.tick(time.delta());
+        if crossfade.timer.finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        let alpha = crossfade.alpha * crossfade.timer.fraction_remaining();
+        sprite.color.set_alpha(alpha);
+        if let Ok(parent_sprite) = parents.get(parent.get()) {
+            sprite.flip_x = parent_sprite.flip_x;
+        }
+    }
+}
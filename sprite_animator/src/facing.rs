@@ -0,0 +1,146 @@
+use bevy::prelude::*;
+
+use crate::SpriteAnimController;
+
+/// Direction an animated sprite is facing.
+/// Sprite sheets are expected to face right, so facing left sets `Sprite::flip_x`.
+///
+/// Example usage:
+/// ```Rust
+/// fn update_facing(mut query: Query<(&mut Facing, &LinearVelocity)>) {
+///     for (mut facing, velocity) in query.iter_mut() {
+///         if let Some(direction) = Facing::from_direction(velocity.x) {
+///             facing.set_if_neq(direction);
+///         }
+///     }
+/// }
+/// ```
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Facing {
+    Left,
+    #[default]
+    Right,
+}
+
+impl Facing {
+    /// Returns the facing matching the sign of a horizontal direction, or `None` if it is zero
+    pub fn from_direction(direction: f32) -> Option<Self> {
+        if direction > 0.0 {
+            Some(Self::Right)
+        } else if direction < 0.0 {
+            Some(Self::Left)
+        } else {
+            None
+        }
+    }
+}
+
+/// Selects direction-specific animations based on the `Facing` of the entity, for sheets that
+/// have dedicated frames for each direction instead of relying on flipping.
+///
+/// The variant of an animation is its name followed by the suffix of the direction. If the
+/// `SpriteAnimController` does not hold the variant, the sprite is flipped instead.
+///
+/// Example usage:
+/// ```Rust
+/// // Plays "Run_L" when facing left, and "Run" when facing right
+/// let run_anim = SpriteAnimData::new("Run", vec![8, 9, 10, 11]);
+/// let run_left_anim = SpriteAnimData::new("Run_L", vec![16, 17, 18, 19]);
+/// commands.spawn((animated_sprite_bundle, Facing::Right, FacingVariants::new("_L", "")));
+/// ```
+#[derive(Component, Debug, Clone)]
+pub struct FacingVariants {
+    left: String,
+    right: String,
+}
+
+impl FacingVariants {
+    /// Creates new variants using the given suffixes for each direction
+    pub fn new(left_suffix: impl Into<String>, right_suffix: impl Into<String>) -> Self {
+        Self {
+            left: left_suffix.into(),
+            right: right_suffix.into(),
+        }
+    }
+
+    /// Returns the suffix used for the given direction
+    pub fn suffix(&self, facing: Facing) -> &str {
+        match facing {
+            Facing::Left => &self.left,
+            Facing::Right => &self.right,
+        }
+    }
+
+    /// Returns the name of the variant of `anim_name` for the given direction
+    pub fn variant(&self, anim_name: &str, facing: Facing) -> String {
+        format!("{}{}", self.base(anim_name), self.suffix(facing))
+    }
+
+    /// Returns `anim_name` without the suffix of its direction
+    pub fn base<'a>(&self, anim_name: &'a str) -> &'a str {
+        [&self.left, &self.right]
+            .into_iter()
+            .filter(|suffix| !suffix.is_empty())
+            .find_map(|suffix| anim_name.strip_suffix(suffix.as_str()))
+            .unwrap_or(anim_name)
+    }
+}
+
+/// System that flips sprites based on their `Facing`, or plays the variant of their current
+/// animation matching it. Switching to a variant keeps the progress of the animation.
+#[allow(clippy::type_complexity)]
+pub(crate) fn apply_facing(
+    mut query: Query<(
+        &Facing,
+        &mut Sprite,
+        Option<&FacingVariants>,
+        Option<&mut SpriteAnimController>,
+    )>,
+) {
+    for (facing, mut sprite, variants, controller) in query.iter_mut() {
+        let mut flip_x = *facing == Facing::Left;
+        if let (Some(variants), Some(mut ac)) = (variants, controller) {
+            let variant = variants.variant(ac.current().name(), *facing);
+            if ac.has_animation(&variant) {
+                if variant != ac.current().name() {
+                    ac.swap_animation(&variant).ok();
+                }
+                flip_x = false;
+            }
+        }
+        if sprite.flip_x != flip_x {
+            sprite.flip_x = flip_x;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_utils::TestApp, SpriteAnimData};
+
+    #[test]
+    fn variants_keep_the_displayed_frame_and_its_time() {
+        let mut app = TestApp::new();
+        let mut controller = SpriteAnimController::default()
+            .with_anim(SpriteAnimData::new("Run", vec![10, 11, 12, 13]))
+            .with_anim(SpriteAnimData::new("Run_L", vec![20, 21, 22, 23]));
+        controller.play("Run").unwrap();
+        controller.set_speed(0.5);
+        let entity = app.spawn(controller);
+        app.app.world_mut().entity_mut(entity).insert((
+            Sprite::default(),
+            Facing::Right,
+            FacingVariants::new("_L", ""),
+        ));
+        assert_eq!(app.frames(entity, 3), vec![10, 10, 11]);
+
+        *app.app
+            .world_mut()
+            .get_mut::<Facing>(entity)
+            .unwrap() = Facing::Left;
+        assert_eq!(app.frames(entity, 4), vec![21, 22, 22, 23]);
+        let sprite = app.app.world().get::<Sprite>(entity).unwrap();
+        assert!(!sprite.flip_x);
+    }
+}
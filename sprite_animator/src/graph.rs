@@ -0,0 +1,330 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{SpriteAnimController, SpriteAnimError, SpriteAnimKey};
+
+/// Value of a parameter of a `SpriteAnimGraph`.
+/// Triggers only last until the next evaluation of the graph, whether a transition fires or not.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpriteAnimParam {
+    Bool(bool),
+    Float(f32),
+    Trigger(bool),
+}
+
+/// Condition that must be met for a `SpriteAnimTransition` to fire.
+/// Missing bools and triggers are treated as `false`, and missing floats as `0.0`.
+#[derive(Debug, Clone)]
+pub enum SpriteAnimCondition {
+    Bool(String, bool),
+    FloatGreater(String, f32),
+    FloatLess(String, f32),
+    Trigger(String),
+}
+
+/// Transition between two states of a `SpriteAnimGraph`.
+/// A transition from a state to itself restarts its animation every time it fires, so it should
+/// use a trigger, or wait for the animation to finish. Transitions created with `from_any` never
+/// fire from their own target state.
+///
+/// Example usage:
+/// ```Rust
+/// let to_run = SpriteAnimTransition::new("Idle", "Run").when_float_greater("speed", 0.1);
+/// let to_land = SpriteAnimTransition::new("Fall", "Land").when_bool("grounded", true);
+/// let to_hurt = SpriteAnimTransition::from_any("Hurt").when_trigger("hit");
+/// let to_idle = SpriteAnimTransition::new("Attack", "Idle").wait_for_finish();
+/// ```
+#[derive(Debug, Clone)]
+pub struct SpriteAnimTransition {
+    from: Option<String>,
+    to: String,
+    conditions: Vec<SpriteAnimCondition>,
+    wait_for_finish: bool,
+}
+
+impl SpriteAnimTransition {
+    /// Creates a transition from the state `from` to the state `to`
+    pub fn new(from: impl SpriteAnimKey, to: impl SpriteAnimKey) -> Self {
+        Self {
+            from: Some(from.anim_name().to_string()),
+            to: to.anim_name().to_string(),
+            conditions: Vec::new(),
+            wait_for_finish: false,
+        }
+    }
+
+    /// Creates a transition from any state, except `to` itself, to the state `to`
+    pub fn from_any(to: impl SpriteAnimKey) -> Self {
+        Self {
+            from: None,
+            ..Self::new("", to)
+        }
+    }
+
+    /// Adds a condition to the transition. Every condition must be met for it to fire.
+    pub fn when(mut self, condition: SpriteAnimCondition) -> Self {
+        self.conditions.push(condition);
+        self
+    }
+
+    /// Fires only if the bool parameter `name` is equal to `value`
+    pub fn when_bool(self, name: impl Into<String>, value: bool) -> Self {
+        self.when(SpriteAnimCondition::Bool(name.into(), value))
+    }
+
+    /// Fires only if the float parameter `name` is greater than `value`
+    pub fn when_float_greater(self, name: impl Into<String>, value: f32) -> Self {
+        self.when(SpriteAnimCondition::FloatGreater(name.into(), value))
+    }
+
+    /// Fires only if the float parameter `name` is less than `value`
+    pub fn when_float_less(self, name: impl Into<String>, value: f32) -> Self {
+        self.when(SpriteAnimCondition::FloatLess(name.into(), value))
+    }
+
+    /// Fires only if the trigger `name` is set, and consumes it
+    pub fn when_trigger(self, name: impl Into<String>) -> Self {
+        self.when(SpriteAnimCondition::Trigger(name.into()))
+    }
+
+    /// Fires only once the current animation is finished.
+    /// Only animations that can finish (see `SpriteAnimMode::can_finish`) will ever let it fire.
+    pub fn wait_for_finish(mut self) -> Self {
+        self.wait_for_finish = true;
+        self
+    }
+}
+
+/// Animation state machine driving a `SpriteAnimController`.
+///
+/// States are the names of the `SpriteAnimData` held by the controller. Gameplay systems only set
+/// the parameters of the graph, and the transitions take care of changing the animation.
+/// Transitions are checked in the order they were added, and at most one fires per update.
+///
+/// Example usage:
+/// ```Rust
+/// // Creation
+/// let graph = SpriteAnimGraph::new("Idle")
+///     .with_transition(SpriteAnimTransition::new("Idle", "Run").when_bool("running", true))
+///     .with_transition(SpriteAnimTransition::new("Run", "Idle").when_bool("running", false))
+///     .with_transition(SpriteAnimTransition::from_any("Jump").when_trigger("jump"))
+///     .with_return("Jump", "Idle");
+/// commands.spawn((animated_sprite_bundle, graph));
+///
+/// // Setting parameters through systems
+/// fn update_params(mut graphs: Query<(&mut SpriteAnimGraph, &LinearVelocity)>) {
+///     for (mut graph, velocity) in graphs.iter_mut() {
+///         graph.set_bool("running", velocity.x.abs() > 0.1);
+///     }
+/// }
+/// ```
+#[derive(Component, Debug, Clone)]
+pub struct SpriteAnimGraph {
+    state: String,
+    params: HashMap<String, SpriteAnimParam>,
+    transitions: Vec<SpriteAnimTransition>,
+    returns: HashMap<String, String>,
+}
+
+impl SpriteAnimGraph {
+    /// Creates a new graph starting in the state `initial`
+    pub fn new(initial: impl SpriteAnimKey) -> Self {
+        Self {
+            state: initial.anim_name().to_string(),
+            params: HashMap::new(),
+            transitions: Vec::new(),
+            returns: HashMap::new(),
+        }
+    }
+
+    /// Adds a transition to the graph on instantiation
+    pub fn with_transition(mut self, transition: SpriteAnimTransition) -> Self {
+        self.transitions.push(transition);
+        self
+    }
+
+    /// Automatically goes back to the state `to` once the animation of the state `from` is
+    /// finished. Transitions added with `with_transition` take priority.
+    pub fn with_return(mut self, from: impl SpriteAnimKey, to: impl SpriteAnimKey) -> Self {
+        self.returns
+            .insert(from.anim_name().to_string(), to.anim_name().to_string());
+        self
+    }
+
+    /// Returns the current state of the graph
+    pub fn state(&self) -> &str {
+        &self.state
+    }
+
+    /// Returns true if the current state of the graph is `state`
+    pub fn is_in(&self, state: impl SpriteAnimKey) -> bool {
+        self.state == state.anim_name()
+    }
+
+    /// Sets the value of a bool parameter
+    pub fn set_bool(&mut self, name: impl Into<String>, value: bool) {
+        self.params
+            .insert(name.into(), SpriteAnimParam::Bool(value));
+    }
+
+    /// Sets the value of a float parameter
+    pub fn set_float(&mut self, name: impl Into<String>, value: f32) {
+        self.params
+            .insert(name.into(), SpriteAnimParam::Float(value));
+    }
+
+    /// Sets a trigger, which is consumed by the next evaluation of the graph
+    pub fn set_trigger(&mut self, name: impl Into<String>) {
+        self.params
+            .insert(name.into(), SpriteAnimParam::Trigger(true));
+    }
+
+    /// Returns the value of a bool parameter, or `false` if it was never set
+    pub fn get_bool(&self, name: &str) -> bool {
+        matches!(self.params.get(name), Some(SpriteAnimParam::Bool(true)))
+    }
+
+    /// Returns the value of a float parameter, or `0.0` if it was never set
+    pub fn get_float(&self, name: &str) -> f32 {
+        match self.params.get(name) {
+            Some(SpriteAnimParam::Float(value)) => *value,
+            _ => 0.0,
+        }
+    }
+
+    /// Returns true if the trigger is currently set
+    pub fn is_triggered(&self, name: &str) -> bool {
+        matches!(self.params.get(name), Some(SpriteAnimParam::Trigger(true)))
+    }
+
+    fn is_met(&self, condition: &SpriteAnimCondition) -> bool {
+        match condition {
+            SpriteAnimCondition::Bool(name, value) => self.get_bool(name) == *value,
+            SpriteAnimCondition::FloatGreater(name, value) => self.get_float(name) > *value,
+            SpriteAnimCondition::FloatLess(name, value) => self.get_float(name) < *value,
+            SpriteAnimCondition::Trigger(name) => self.is_triggered(name),
+        }
+    }
+
+    /// Returns the state the graph should move to, if any, and consumes every trigger
+    fn next_state(&mut self, finished: bool) -> Option<String> {
+        let transition = self.transitions.iter().find(|transition| {
+            transition
+                .from
+                .as_ref()
+                .map_or(transition.to != self.state, |from| *from == self.state)
+                && (!transition.wait_for_finish || finished)
+                && transition
+                    .conditions
+                    .iter()
+                    .all(|condition| self.is_met(condition))
+        });
+
+        let next = match transition {
+            Some(transition) => Some(transition.to.clone()),
+            None => finished
+                .then(|| self.returns.get(&self.state).cloned())
+                .flatten(),
+        };
+
+        for param in self.params.values_mut() {
+            if let SpriteAnimParam::Trigger(triggered) = param {
+                *triggered = false;
+            }
+        }
+        next
+    }
+}
+
+/// System that moves every `SpriteAnimGraph` through its transitions and plays the animation of
+/// its current state. Uses `SpriteAnimController::play`, so transitions crossfade if the
+/// controller was set up to.
+pub(crate) fn update_anim_graphs(
+    mut query: Query<(&mut SpriteAnimGraph, &mut SpriteAnimController)>,
+) {
+    for (mut graph, mut ac) in query.iter_mut() {
+        let next = graph.next_state(ac.is_finished());
+        let is_new = graph.is_added() || ac.is_added();
+        let mut is_self_transition = false;
+        if let Some(next) = next {
+            is_self_transition = next == graph.state;
+            graph.state = next;
+        } else if !is_new {
+            continue;
+        }
+        // Newly spawned entities cut directly to their initial state, and self-transitions
+        // restart the animation
+        let result = if is_new || is_self_transition {
+            ac.restart(&graph.state)
+        } else {
+            ac.play(&graph.state)
+        };
+        match result {
+            Ok(()) | Err(SpriteAnimError::AlreadyPlaying(_)) => {}
+            Err(err) => error!("{}", err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_utils::TestApp, SpriteAnimData, SpriteAnimMode};
+
+    const FRAMES: [usize; 4] = [10, 11, 12, 13];
+
+    fn spawn(app: &mut TestApp, graph: SpriteAnimGraph) -> Entity {
+        let controller = SpriteAnimController::default()
+            .with_anim(SpriteAnimData::new("Idle", FRAMES.to_vec()))
+            .with_anim(
+                SpriteAnimData::new("Attack", FRAMES.to_vec()).with_mode(SpriteAnimMode::Once),
+            );
+        let entity = app.spawn(controller);
+        app.app
+            .world_mut()
+            .entity_mut(entity)
+            .insert(graph);
+        entity
+    }
+
+    fn graph_mut(app: &mut TestApp, entity: Entity) -> Mut<'_, SpriteAnimGraph> {
+        app.app
+            .world_mut()
+            .get_mut::<SpriteAnimGraph>(entity)
+            .unwrap()
+    }
+
+    #[test]
+    fn self_transitions_restart_the_animation() {
+        let mut app = TestApp::new();
+        let graph = SpriteAnimGraph::new("Attack")
+            .with_transition(SpriteAnimTransition::new("Attack", "Attack").when_trigger("attack"))
+            .with_transition(SpriteAnimTransition::from_any("Attack").when_trigger("hit"));
+        let entity = spawn(&mut app, graph);
+        assert_eq!(app.frames(entity, 3), vec![10, 11, 12]);
+
+        graph_mut(&mut app, entity).set_trigger("hit");
+        assert_eq!(app.frames(entity, 2), vec![13, 13]);
+
+        graph_mut(&mut app, entity).set_trigger("attack");
+        assert_eq!(app.frames(entity, 2), vec![10, 11]);
+    }
+
+    #[test]
+    fn unused_triggers_are_consumed() {
+        let mut app = TestApp::new();
+        let graph = SpriteAnimGraph::new("Attack")
+            .with_transition(SpriteAnimTransition::from_any("Attack").when_trigger("attack"))
+            .with_return("Attack", "Idle");
+        let entity = spawn(&mut app, graph);
+        app.step();
+
+        // Setting the trigger while already attacking must not attack again once idle
+        graph_mut(&mut app, entity).set_trigger("attack");
+        app.step();
+        assert!(!graph_mut(&mut app, entity).is_triggered("attack"));
+        app.frames(entity, 4);
+        assert!(graph_mut(&mut app, entity).is_in("Idle"));
+        app.step();
+        assert!(graph_mut(&mut app, entity).is_in("Idle"));
+    }
+}
@@ -0,0 +1,114 @@
+/// Anything that can name an animation.
+///
+/// Implemented for strings, which keeps animations loaded from files usable by name, and for
+/// enums declared with `sprite_anim_key!`, which lets the compiler catch invalid names.
+///
+/// Example usage:
+/// ```Rust
+/// sprite_anim_key! {
+///     #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///     pub enum PlayerAnim {
+///         Idle,
+///         Run,
+///         Jump,
+///     }
+/// }
+///
+/// let idle_anim = SpriteAnimData::new(PlayerAnim::Idle, vec![0, 1, 2, 3]);
+/// ac.play(PlayerAnim::Run).ok();
+/// ac.play("Run").ok(); // Same animation
+/// ```
+pub trait SpriteAnimKey {
+    /// Returns the name of the animation
+    fn anim_name(&self) -> &str;
+}
+
+impl SpriteAnimKey for str {
+    fn anim_name(&self) -> &str {
+        self
+    }
+}
+
+impl SpriteAnimKey for String {
+    fn anim_name(&self) -> &str {
+        self
+    }
+}
+
+impl<T: SpriteAnimKey + ?Sized> SpriteAnimKey for &T {
+    fn anim_name(&self) -> &str {
+        (**self).anim_name()
+    }
+}
+
+/// Declares an enum whose variants are animation keys, named after the variants.
+///
+/// Example usage:
+/// ```Rust
+/// sprite_anim_key! {
+///     #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///     pub enum EnemyAnim {
+///         Idle,
+///         Attack,
+///     }
+/// }
+///
+/// assert_eq!(EnemyAnim::Attack.anim_name(), "Attack");
+/// ```
+#[macro_export]
+macro_rules! sprite_anim_key {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident {
+            $($(#[$variant_meta:meta])* $variant:ident),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis enum $name {
+            $($(#[$variant_meta])* $variant),*
+        }
+
+        impl $crate::SpriteAnimKey for $name {
+            fn anim_name(&self) -> &str {
+                match self {
+                    $(Self::$variant => stringify!($variant)),*
+                }
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{SpriteAnimController, SpriteAnimData, SpriteAnimError, SpriteAnimKey};
+
+    sprite_anim_key! {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        enum TestAnim {
+            Idle,
+            /// Documented variant
+            Run,
+        }
+    }
+
+    #[test]
+    fn enum_and_string_keys_are_interchangeable() {
+        assert_eq!(TestAnim::Idle.anim_name(), "Idle");
+        assert_eq!(TestAnim::Run.anim_name(), "Run");
+
+        let mut controller = SpriteAnimController::default()
+            .with_anim(SpriteAnimData::new(TestAnim::Idle, vec![0, 1]))
+            .with_anim(SpriteAnimData::new("Run", vec![2, 3]));
+        assert!(controller.has_animation(TestAnim::Idle));
+        assert!(controller.has_animation(String::from("Run")));
+
+        controller.play(TestAnim::Run).unwrap();
+        assert_eq!(controller.current().name(), "Run");
+        assert!(matches!(
+            controller.play("Run"),
+            Err(SpriteAnimError::AlreadyPlaying(_))
+        ));
+        controller.restart(TestAnim::Idle).unwrap();
+        assert_eq!(controller.current().name(), TestAnim::Idle.anim_name());
+    }
+}
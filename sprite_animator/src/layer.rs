@@ -0,0 +1,243 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{Facing, FacingVariants, SpriteAnimController, SpriteAnimKey};
+
+/// Overlay displayed in lockstep with the `SpriteAnimController` of its parent, such as
+/// equipment or effects drawn on a different sheet.
+///
+/// Every animation of the parent is mapped to the frames of the layer's own sheet, by position.
+/// The layer is hidden while the parent plays an animation it has no mapping for, unless
+/// `with_parent_frames` is used. The layer also mirrors the `Sprite::flip_x` of its parent.
+///
+/// While the parent plays a variant from its `FacingVariants`, such as "Run_L", the layer uses
+/// the mapping of the variant if it has one. Otherwise it uses the mapping of the base
+/// animation, "Run", flipped when the parent faces left.
+///
+/// Example usage:
+/// ```Rust
+/// let layer = SpriteAnimLayer::new()
+///     .with_anim("Idle", vec![0, 1, 2, 3])
+///     .with_anim("Attack", vec![4, 5, 6, 7, 8]);
+///
+/// commands.entity(player).with_children(|parent| {
+///     parent.spawn(SpriteAnimLayerBundle {
+///         sprite_bundle: SpriteBundle {
+///             texture: asset_server.load("sword.png"),
+///             transform: Transform::from_xyz(0.0, 0.0, 0.1),
+///             ..default()
+///         },
+///         texture_atlas: TextureAtlas::from(sword_layout_handle),
+///         layer,
+///     });
+/// });
+/// ```
+#[derive(Component, Debug, Clone, Default)]
+pub struct SpriteAnimLayer {
+    frames: HashMap<String, Vec<usize>>,
+    parent_frames: bool,
+}
+
+impl SpriteAnimLayer {
+    /// Creates a layer without any mapping
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Maps the animation `anim` of the parent to the given frames of the layer's sheet.
+    /// The frame at each position is displayed when the parent displays the frame at the same
+    /// position of its own animation.
+    pub fn with_anim(mut self, anim: impl SpriteAnimKey, frames: Vec<usize>) -> Self {
+        self.frames
+            .insert(anim.anim_name().to_string(), frames);
+        self
+    }
+
+    /// Displays the same frames as the parent for animations without a mapping, for sheets that
+    /// share the layout of the parent's sheet.
+    pub fn with_parent_frames(mut self) -> Self {
+        self.parent_frames = true;
+        self
+    }
+
+    /// Returns the frame of the layer's sheet mapped to the frame at `index` of the parent's
+    /// animation, if any. Mappings shorter than the parent's animation hold their last frame.
+    fn mapped_frame(&self, anim_name: &str, index: usize) -> Option<usize> {
+        let frames = self.frames.get(anim_name)?;
+        frames.get(index).or(frames.last()).copied()
+    }
+}
+
+/// Bundle of components required to display a `SpriteAnimLayer`.
+/// Must be spawned as a child of an entity with a `SpriteAnimController`.
+#[derive(Bundle)]
+pub struct SpriteAnimLayerBundle {
+    pub sprite_bundle: SpriteBundle,
+    pub texture_atlas: TextureAtlas,
+    pub layer: SpriteAnimLayer,
+}
+
+/// System that displays the frame of every `SpriteAnimLayer` matching the frame displayed by
+/// its parent.
+#[allow(clippy::type_complexity)]
+pub(crate) fn sync_anim_layers(
+    parents: Query<(
+        &SpriteAnimController,
+        Option<&Sprite>,
+        Option<&Facing>,
+        Option<&FacingVariants>,
+    )>,
+    mut layers: Query<
+        (
+            &Parent,
+            &SpriteAnimLayer,
+            &mut TextureAtlas,
+            &mut Visibility,
+            Option<&mut Sprite>,
+        ),
+        Without<SpriteAnimController>,
+    >,
+) {
+    for (parent, layer, mut ta, mut visibility, sprite) in layers.iter_mut() {
+        let Ok((ac, parent_sprite, facing, variants)) = parents.get(parent.get()) else {
+            continue;
+        };
+        let anim_name = ac.current().name();
+        let base_name = variants
+            .map(|variants| variants.base(anim_name))
+            .filter(|base_name| *base_name != anim_name);
+        let mut flip_x = parent_sprite.map(|parent_sprite| parent_sprite.flip_x);
+        let frame = match layer.mapped_frame(anim_name, ac.frame_index()) {
+            Some(frame) => Some(frame),
+            None => match base_name.and_then(|name| layer.mapped_frame(name, ac.frame_index())) {
+                Some(frame) => {
+                    flip_x = facing.map(|facing| *facing == Facing::Left);
+                    Some(frame)
+                }
+                None => layer.parent_frames.then_some(ac.frame()),
+            },
+        };
+
+        match frame {
+            Some(frame) => {
+                if ta.index != frame {
+                    ta.index = frame;
+                }
+                visibility.set_if_neq(Visibility::Inherited);
+            }
+            None => {
+                visibility.set_if_neq(Visibility::Hidden);
+            }
+        }
+        if let (Some(mut sprite), Some(flip_x)) = (sprite, flip_x) {
+            if sprite.flip_x != flip_x {
+                sprite.flip_x = flip_x;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_utils::TestApp, SpriteAnimData};
+
+    fn controller() -> SpriteAnimController {
+        let mut controller = SpriteAnimController::default()
+            .with_anim(SpriteAnimData::new("Idle", vec![0, 1, 2]))
+            .with_anim(SpriteAnimData::new("Attack", vec![3, 4]))
+            .with_anim(SpriteAnimData::new("Run", vec![5, 6]))
+            .with_anim(SpriteAnimData::new("Run_L", vec![7, 8]));
+        controller.play("Idle").unwrap();
+        controller
+    }
+
+    /// Spawns `layer` as a child of a new animated sprite, and returns both entities
+    fn spawn(app: &mut TestApp, layer: SpriteAnimLayer) -> (Entity, Entity) {
+        let parent = app.spawn(controller());
+        app.app
+            .world_mut()
+            .entity_mut(parent)
+            .insert(Sprite::default());
+        let child = app
+            .app
+            .world_mut()
+            .spawn((
+                layer,
+                TextureAtlas::default(),
+                Visibility::default(),
+                Sprite::default(),
+            ))
+            .set_parent(parent)
+            .id();
+        (parent, child)
+    }
+
+    fn visibility(app: &TestApp, entity: Entity) -> Visibility {
+        *app.app.world().get::<Visibility>(entity).unwrap()
+    }
+
+    fn flip_x(app: &TestApp, entity: Entity) -> bool {
+        app.app
+            .world()
+            .get::<Sprite>(entity)
+            .unwrap()
+            .flip_x
+    }
+
+    #[test]
+    fn layers_follow_their_parent() {
+        let mut app = TestApp::new();
+        let layer = SpriteAnimLayer::new().with_anim("Idle", vec![10, 11]);
+        let (parent, child) = spawn(&mut app, layer);
+
+        assert_eq!(app.frames(child, 4), vec![10, 11, 11, 10]);
+        assert_eq!(visibility(&app, child), Visibility::Inherited);
+
+        app.controller_mut(parent).play("Attack").unwrap();
+        app.step();
+        assert_eq!(visibility(&app, child), Visibility::Hidden);
+
+        app.app
+            .world_mut()
+            .get_mut::<Sprite>(parent)
+            .unwrap()
+            .flip_x = true;
+        app.controller_mut(parent).play("Idle").unwrap();
+        app.step();
+        assert_eq!(visibility(&app, child), Visibility::Inherited);
+        assert!(flip_x(&app, child));
+    }
+
+    #[test]
+    fn parent_frames_fill_missing_mappings() {
+        let mut app = TestApp::new();
+        let layer = SpriteAnimLayer::new()
+            .with_anim("Attack", vec![20, 21])
+            .with_parent_frames();
+        let (parent, child) = spawn(&mut app, layer);
+
+        assert_eq!(app.frames(child, 3), vec![0, 1, 2]);
+        app.controller_mut(parent).play("Attack").unwrap();
+        assert_eq!(app.frames(child, 2), vec![20, 21]);
+        assert_eq!(visibility(&app, child), Visibility::Inherited);
+    }
+
+    #[test]
+    fn facing_variants_use_the_base_mapping() {
+        let mut app = TestApp::new();
+        let layer = SpriteAnimLayer::new().with_anim("Run", vec![30, 31]);
+        let (parent, child) = spawn(&mut app, layer);
+        app.app
+            .world_mut()
+            .entity_mut(parent)
+            .insert((Facing::Left, FacingVariants::new("_L", "")));
+        app.controller_mut(parent).play("Run").unwrap();
+
+        assert_eq!(app.frames(child, 2), vec![30, 31]);
+        assert_eq!(app.controller_mut(parent).current().name(), "Run_L");
+        assert_eq!(visibility(&app, child), Visibility::Inherited);
+        // The parent plays its left variant unflipped, so the layer flips instead
+        assert!(!flip_x(&app, parent));
+        assert!(flip_x(&app, child));
+    }
+}
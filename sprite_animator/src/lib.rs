@@ -1,12 +1,54 @@
+mod aseprite;
+mod crossfade;
+mod facing;
+mod graph;
+mod key;
+mod layer;
+mod library;
+mod sheet;
+mod target;
+#[cfg(test)]
+mod test_utils;
+
 use core::fmt;
-use std::time::Duration;
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    ops::Deref,
+    sync::Arc,
+    time::Duration,
+};
+
+use bevy::{
+    ecs::{schedule::InternedScheduleLabel, schedule::ScheduleLabel, system::SystemParam},
+    prelude::*,
+    utils::HashMap,
+};
+use serde::{Deserialize, Serialize};
 
-use bevy::{prelude::*, utils::HashMap};
+pub use aseprite::{AsepriteLoader, AsepriteLoaderError, AsepriteSheet};
+pub use crossfade::SpriteAnimCrossfade;
+pub use facing::{Facing, FacingVariants};
+pub use graph::{SpriteAnimCondition, SpriteAnimGraph, SpriteAnimParam, SpriteAnimTransition};
+pub use key::SpriteAnimKey;
+pub use layer::{SpriteAnimLayer, SpriteAnimLayerBundle};
+use library::ControllerLibrary;
+pub use library::SpriteAnimLibrary;
+pub use sheet::{
+    SpriteAnimSheet, SpriteAnimSheetBundle, SpriteAnimSheetLoader, SpriteAnimSheetLoaderError,
+};
+pub use target::{SpriteAnimAppExt, SpriteAnimImages, SpriteAnimTarget};
 
 #[derive(Debug)]
 pub enum SpriteAnimError {
     NoFrames(String),
     DoesNotExist(String),
+    AlreadyPlaying(String),
+    /// Animation name, frame, and number of frames in the `TextureAtlasLayout`
+    FrameOutOfRange(String, usize, usize),
+    DuplicateName(String),
+    /// Animation name, and texture that is not part of the `TextureAtlasLayout`
+    TextureNotInLayout(String, AssetId<Image>),
 }
 
 impl fmt::Display for SpriteAnimError {
@@ -22,6 +64,24 @@ impl fmt::Display for SpriteAnimError {
                 "The animation \"{}\" does not exist. Playing default animation instead.",
                 msg
             ),
+            Self::AlreadyPlaying(msg) => {
+                write!(f, "The animation \"{}\" is already playing", msg)
+            }
+            Self::FrameOutOfRange(msg, frame, len) => write!(
+                f,
+                "The frame {} of animation \"{}\" is out of range, the texture atlas layout only has {} frames",
+                frame, msg, len
+            ),
+            Self::DuplicateName(msg) => write!(
+                f,
+                "An animation named \"{}\" already exists in the collection",
+                msg
+            ),
+            Self::TextureNotInLayout(msg, texture) => write!(
+                f,
+                "The texture {:?} of animation \"{}\" is not part of the texture atlas layout",
+                texture, msg
+            ),
         }
     }
 }
@@ -29,27 +89,90 @@ impl fmt::Display for SpriteAnimError {
 /// Repeating: Animation plays continuously.
 /// Once: Animation plays once.
 /// Mirror: Animation plays continuously forward and backwards.
-#[derive(Debug, Clone)]
+/// MirrorOnce: Animation plays forward, then backwards, and stops on its first frame.
+/// Loop(count): Animation plays `count` times in total, and stops on its last frame.
+/// LoopFrom(index): Animation plays once, then loops continuously from the frame at `index`.
+/// Random: A random frame is displayed every time, never the same one twice in a row.
+#[derive(Reflect, Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[reflect(Debug, Default, PartialEq)]
 pub enum SpriteAnimMode {
+    #[default]
     Repeating,
     Once,
     Mirror,
+    MirrorOnce,
+    Loop(u32),
+    LoopFrom(usize),
+    Random,
+}
+
+impl SpriteAnimMode {
+    /// Returns true if animations using this mode eventually stop on a frame
+    pub fn can_finish(&self) -> bool {
+        matches!(self, Self::Once | Self::MirrorOnce | Self::Loop(_))
+    }
+}
+
+/// Event sent by `play_animation` whenever a frame that has a marker is displayed.
+///
+/// Example usage:
+/// ```Rust
+/// fn spawn_hitbox(mut marker_events: EventReader<SpriteAnimMarkerEvent>) {
+///     for event in marker_events.read() {
+///         if event.animation == "Attack" && event.marker == "hit" {
+///             // Spawn the hitbox for `event.entity`
+///         }
+///     }
+/// }
+/// ```
+#[derive(Event, Debug, Clone)]
+pub struct SpriteAnimMarkerEvent {
+    pub entity: Entity,
+    pub animation: String,
+    pub marker: String,
+}
+
+/// Event sent by `play_animation` when an animation that can finish, such as
+/// `SpriteAnimMode::Once`, displays its last frame.
+#[derive(Event, Debug, Clone)]
+pub struct SpriteAnimFinishedEvent {
+    pub entity: Entity,
+    pub animation: String,
 }
 
 /// Holds the data for a single animation.
 ///
+/// By default, every frame is displayed for `1 / fps` seconds, where `fps` comes from the
+/// `SpriteAnimController`. An animation can override that framerate with `with_fps`, and single
+/// frames can be held longer (or shorter) with `with_frame_duration`.
+///
+/// Markers added with `with_marker` send a `SpriteAnimMarkerEvent` every time their frame is
+/// displayed.
+///
+/// Animations only describe what to play. The progress of the animation being played is kept in
+/// the `SpriteAnimState` of each `SpriteAnimController`, so animations can be shared through a
+/// `SpriteAnimLibrary`.
+///
 /// Example usage:
 /// ```Rust
 /// let idle_anim = SpriteAnimData::new("Idle", vec![0,1,2,3]);
-/// let attack_anim = SpriteAnimData::new("Attack", vec![4,5,6,7]).with_mode(SpriteAnimMode::Once);
+/// let run_anim = SpriteAnimData::from_grid("Run", 7, 1, 1, 6); // vec![8, 9, 10, 11, 12, 13]
+/// let crouch_anim = SpriteAnimData::from_range("Crouch", 4..8).concat(idle_anim.clone());
+/// let attack_anim = SpriteAnimData::new("Attack", vec![4,5,6,7])
+///     .with_mode(SpriteAnimMode::Once)
+///     .with_fps(12.0)
+///     .with_frame_duration(0, Duration::from_millis(300))
+///     .with_marker(2, "hit");
 /// ```
-#[derive(Debug, Clone)]
+#[derive(Reflect, Debug, Clone, Serialize, Deserialize)]
+#[reflect(Debug, Default)]
 pub struct SpriteAnimData {
     name: String,
     frames: Vec<usize>,
-    index: usize,
     mode: SpriteAnimMode,
-    forward: bool,
+    fps: Option<f32>,
+    frame_durations: HashMap<usize, Duration>,
+    markers: HashMap<usize, Vec<String>>,
 }
 
 impl Default for SpriteAnimData {
@@ -57,77 +180,403 @@ impl Default for SpriteAnimData {
         Self {
             name: "Default".into(),
             frames: vec![0],
-            index: 0,
             mode: SpriteAnimMode::Once,
-            forward: true,
+            fps: None,
+            frame_durations: HashMap::new(),
+            markers: HashMap::new(),
         }
     }
 }
 
 impl SpriteAnimData {
     /// Creates a new `SpriteAnimData` struct and defaults to `SpriteAnimMode::Repeating`.
-    pub fn new(name: impl Into<String>, frames: Vec<usize>) -> Self {
+    pub fn new(name: impl SpriteAnimKey, frames: Vec<usize>) -> Self {
         Self {
-            name: name.into(),
+            name: name.anim_name().to_string(),
             frames,
             mode: SpriteAnimMode::Repeating,
             ..default()
         }
     }
 
+    /// Creates a new `SpriteAnimData` struct from a range, or any other sequence of frames.
+    ///
+    /// Example usage:
+    /// ```Rust
+    /// let run_anim = SpriteAnimData::from_range("Run", 8..=13);
+    /// ```
+    pub fn from_range(name: impl SpriteAnimKey, frames: impl IntoIterator<Item = usize>) -> Self {
+        Self::new(name, frames.into_iter().collect())
+    }
+
+    /// Creates a new `SpriteAnimData` struct holding every frame of `row` in a grid of `columns`
+    /// columns, such as a `TextureAtlasLayout` created with `from_grid`.
+    pub fn from_grid_row(name: impl SpriteAnimKey, columns: usize, row: usize) -> Self {
+        Self::from_grid(name, columns, row, 0, columns)
+    }
+
+    /// Creates a new `SpriteAnimData` struct holding `count` consecutive frames of a grid of
+    /// `columns` columns, starting at `row` and `column`. Frames continue on the next row once
+    /// the end of a row is reached.
+    pub fn from_grid(
+        name: impl SpriteAnimKey,
+        columns: usize,
+        row: usize,
+        column: usize,
+        count: usize,
+    ) -> Self {
+        let start = row * columns + column;
+        Self::from_range(name, start..start + count)
+    }
+
+    /// Creates a new `SpriteAnimData` struct from textures that were added to a
+    /// `TextureAtlasLayout` by a `TextureAtlasBuilder`, such as sheets made of separate files.
+    /// Returns an error if one of the textures is not part of the layout.
+    pub fn from_atlas_textures(
+        name: impl SpriteAnimKey,
+        layout: &TextureAtlasLayout,
+        textures: impl IntoIterator<Item = impl Into<AssetId<Image>>>,
+    ) -> Result<Self, SpriteAnimError> {
+        let name = name.anim_name().to_string();
+        let frames = textures
+            .into_iter()
+            .map(|texture| {
+                let texture = texture.into();
+                layout
+                    .get_texture_index(texture)
+                    .ok_or_else(|| SpriteAnimError::TextureNotInLayout(name.clone(), texture))
+            })
+            .collect::<Result<Vec<usize>, SpriteAnimError>>()?;
+        Ok(Self::new(name, frames))
+    }
+
+    /// Reverses the order of the frames, along with their durations and markers.
+    /// Durations and markers set past the last frame are dropped.
+    pub fn reversed(mut self) -> Self {
+        let len = self.frames.len();
+        self.frames.reverse();
+        self.frame_durations = self
+            .frame_durations
+            .into_iter()
+            .filter_map(|(index, duration)| Some((len.checked_sub(index + 1)?, duration)))
+            .collect();
+        self.markers = self
+            .markers
+            .into_iter()
+            .filter_map(|(index, markers)| Some((len.checked_sub(index + 1)?, markers)))
+            .collect();
+        self
+    }
+
+    /// Appends the frames of `other`, along with their durations and markers, after the frames
+    /// of this animation. The name, mode and framerate of `other` are ignored.
+    pub fn concat(mut self, other: SpriteAnimData) -> Self {
+        let offset = self.frames.len();
+        self.frames.extend(other.frames);
+        self.frame_durations.extend(
+            other
+                .frame_durations
+                .into_iter()
+                .map(|(index, duration)| (index + offset, duration)),
+        );
+        for (index, markers) in other.markers {
+            self.markers
+                .entry(index + offset)
+                .or_default()
+                .extend(markers);
+        }
+        self
+    }
+
     /// Changes the mode of the animation on instantiation
     pub fn with_mode(mut self, mode: SpriteAnimMode) -> Self {
         self.mode = mode;
         self
     }
 
-    /// Sets the index of the current animation to zero and effectively starts it over
-    pub fn reset(&mut self) {
-        self.index = 0;
+    /// Overrides the framerate of the `SpriteAnimController` for this animation only.
+    /// Framerates that are not strictly positive are ignored.
+    pub fn with_fps(mut self, fps: f32) -> Self {
+        if fps > 0.0 {
+            self.fps = Some(fps);
+        } else {
+            warn!("Ignoring the invalid framerate {} of {}", fps, self.name);
+        }
+        self
+    }
+
+    /// Sets how long the frame at `index` (position in the `frames` vector) stays on screen.
+    /// Takes priority over both the animation's and the controller's framerate.
+    pub fn with_frame_duration(mut self, index: usize, duration: Duration) -> Self {
+        self.frame_durations.insert(index, duration);
+        self
+    }
+
+    /// Adds a named marker on the frame at `index` (position in the `frames` vector).
+    /// A frame can hold multiple markers.
+    pub fn with_marker(mut self, index: usize, marker: impl Into<String>) -> Self {
+        self.markers
+            .entry(index)
+            .or_default()
+            .push(marker.into());
+        self
+    }
+
+    /// Returns the name of the animation
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the frames of the animation
+    pub fn frames(&self) -> &[usize] {
+        &self.frames
+    }
+
+    /// Returns the mode of the animation
+    pub fn mode(&self) -> &SpriteAnimMode {
+        &self.mode
+    }
+
+    /// Returns the framerate override of the animation, if any
+    pub fn fps(&self) -> Option<f32> {
+        self.fps
+    }
+
+    /// Returns how long the frame at `index` should be displayed, if the animation overrides it.
+    pub fn frame_duration(&self, index: usize) -> Option<Duration> {
+        self.frame_durations
+            .get(&index)
+            .copied()
+            .or_else(|| {
+                self.fps
+                    .filter(|fps| *fps > 0.0)
+                    .map(|fps| Duration::from_secs_f32(1.0 / fps))
+            })
+    }
+
+    /// Returns the markers of the frame at `index`
+    pub fn markers(&self, index: usize) -> &[String] {
+        self.markers
+            .get(&index)
+            .map_or(&[], Vec::as_slice)
     }
 
-    /// Gets the frame that should be played, and advances the index to the next frame.
+    /// Checks that the animation has frames, and that they all exist in `layout` if given.
+    /// Returns every problem found.
+    pub fn validate(&self, layout: Option<&TextureAtlasLayout>) -> Vec<SpriteAnimError> {
+        if self.frames.is_empty() {
+            return vec![SpriteAnimError::NoFrames(self.name.clone())];
+        }
+        let Some(layout) = layout else {
+            return Vec::new();
+        };
+        self.frames
+            .iter()
+            .filter(|frame| **frame >= layout.len())
+            .map(|frame| SpriteAnimError::FrameOutOfRange(self.name.clone(), *frame, layout.len()))
+            .collect()
+    }
+
+    /// Gets the frame that should be played, and advances the index of `state` to the next frame.
+    /// When `reversed` is true, the index moves towards the first frame instead.
     /// Returns an error if the `frames` vector is empty.
-    fn get_frame(&mut self) -> Result<&usize, SpriteAnimError> {
+    fn get_frame(
+        &self,
+        state: &mut SpriteAnimState,
+        reversed: bool,
+    ) -> Result<usize, SpriteAnimError> {
         if self.frames.is_empty() {
             return Err(SpriteAnimError::NoFrames(self.name.clone()));
         }
         if self.frames.len() == 1 {
-            return Ok(self.frames.first().unwrap_or(&0));
+            state.finished = self.mode.can_finish();
+            return Ok(self.frames[0]);
         }
-        let frame = self.frames.get(self.index).unwrap();
+        let frame_index = state.index;
+        let last = self.frames.len() - 1;
         match self.mode {
+            SpriteAnimMode::Repeating if reversed => {
+                if state.index > 0 {
+                    state.index -= 1;
+                } else {
+                    state.index = last;
+                }
+            }
             SpriteAnimMode::Repeating => {
-                if self.index < self.frames.len() - 1 {
-                    self.index += 1;
+                if state.index < last {
+                    state.index += 1;
+                } else {
+                    state.index = 0;
+                }
+            }
+            SpriteAnimMode::Once if reversed => {
+                if state.index > 0 {
+                    state.index -= 1;
                 } else {
-                    self.index = 0;
+                    state.finished = true;
                 }
             }
             SpriteAnimMode::Once => {
-                if self.index < self.frames.len() - 1 {
-                    self.index += 1;
+                if state.index < last {
+                    state.index += 1;
+                } else {
+                    state.finished = true;
                 }
             }
+            // Reversing a mirrored animation only flips its current direction, which is
+            // handled by `SpriteAnimController::set_reversed`. `MirrorOnce` animations also
+            // finish on the frame they started from.
             SpriteAnimMode::Mirror => {
-                if self.index == 0 {
-                    self.forward = true;
-                } else if self.index == self.frames.len() - 1 {
-                    self.forward = false;
+                if state.index == 0 {
+                    state.forward = true;
+                } else if state.index == last {
+                    state.forward = false;
+                }
+                if state.forward {
+                    state.index += 1;
+                } else {
+                    state.index = state.index.saturating_sub(1);
+                }
+            }
+            SpriteAnimMode::MirrorOnce if reversed => {
+                if !state.forward && state.index == 0 {
+                    state.forward = true;
+                }
+                if !state.forward {
+                    state.index -= 1;
+                } else if state.index < last {
+                    state.index += 1;
+                } else {
+                    state.finished = true;
+                }
+            }
+            SpriteAnimMode::MirrorOnce => {
+                if state.forward && state.index == last {
+                    state.forward = false;
                 }
-                if self.forward {
-                    self.index += 1;
+                if state.forward {
+                    state.index += 1;
+                } else if state.index > 0 {
+                    state.index -= 1;
+                } else {
+                    state.finished = true;
+                }
+            }
+            SpriteAnimMode::Loop(count) if reversed => {
+                if state.index > 0 {
+                    state.index -= 1;
+                } else {
+                    state.loops += 1;
+                    if state.loops >= count {
+                        state.finished = true;
+                    } else {
+                        state.index = last;
+                    }
+                }
+            }
+            SpriteAnimMode::Loop(count) => {
+                if state.index < last {
+                    state.index += 1;
+                } else {
+                    state.loops += 1;
+                    if state.loops >= count {
+                        state.finished = true;
+                    } else {
+                        state.index = 0;
+                    }
+                }
+            }
+            SpriteAnimMode::LoopFrom(start) if reversed => {
+                if state.index > start {
+                    state.index -= 1;
+                } else {
+                    state.index = last;
+                }
+            }
+            SpriteAnimMode::LoopFrom(start) => {
+                if state.index < last {
+                    state.index += 1;
                 } else {
-                    self.index = self.index.saturating_sub(1);
+                    state.index = start.min(last);
                 }
             }
+            SpriteAnimMode::Random => {
+                state.index = state.random_index(self.frames.len());
+            }
+        }
+        Ok(self.frames[frame_index])
+    }
+}
+
+/// Playback state of the animation played by a `SpriteAnimController`
+#[derive(Reflect, Debug, Clone, Serialize, Deserialize)]
+#[reflect(Debug, Default)]
+pub struct SpriteAnimState {
+    index: usize,
+    forward: bool,
+    finished: bool,
+    loops: u32,
+    rng: u32,
+}
+
+impl Default for SpriteAnimState {
+    fn default() -> Self {
+        Self {
+            index: 0,
+            forward: true,
+            finished: false,
+            loops: 0,
+            rng: 0,
+        }
+    }
+}
+
+impl SpriteAnimState {
+    /// Returns the index (position in the `frames` vector) of the next frame to display
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns true once an animation that can finish, such as `SpriteAnimMode::Once`, has
+    /// displayed its last frame
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Sets the index of the animation to zero and effectively starts it over
+    pub fn reset(&mut self) {
+        self.index = 0;
+        self.forward = true;
+        self.finished = false;
+        self.loops = 0;
+    }
+
+    /// Returns the index of a random frame, different from the current one.
+    /// Uses a xorshift generator seeded randomly the first time it is called.
+    fn random_index(&mut self, len: usize) -> usize {
+        if self.rng == 0 {
+            self.rng = (RandomState::new().build_hasher().finish() as u32).max(1);
+        }
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        let index = self.rng as usize % len;
+        if index == self.index {
+            (index + 1) % len
+        } else {
+            index
         }
-        Ok(frame)
     }
 }
 
 /// Sprite animation controller used to play animations from a sprite sheet.
 ///
+/// The animations are held by a `SpriteAnimLibrary`, which can be shared by any number of
+/// controllers. Each controller only stores the state of its own playback.
+///
+/// Animations are named by any `SpriteAnimKey`: strings, or enums declared with
+/// `sprite_anim_key!` to have the compiler check the names.
+///
 /// Example usage:
 /// ```Rust
 /// // Creation
@@ -137,7 +586,15 @@ impl SpriteAnimData {
 /// // Changing animation through systems
 /// fn change_anim(mut anim_controllers: Query<&mut SpriteAnimController>) {
 ///     for mut ac in anim_controllers.iter_mut() {
-///         ac.set_current_animation("Idle").ok();
+///         // Does nothing if "Idle" is already playing
+///         ac.play("Idle").ok();
+///     }
+/// }
+///
+/// // Restarting an animation through systems
+/// fn attack(mut anim_controllers: Query<&mut SpriteAnimController>) {
+///     for mut ac in anim_controllers.iter_mut() {
+///         ac.restart("Attack").ok();
 ///     }
 /// }
 ///
@@ -151,16 +608,76 @@ impl SpriteAnimData {
 /// // Resetting an animation through systems
 /// fn change_anim(mut anim_controllers: Query<&mut SpriteAnimController>) {
 ///     for mut ac in anim_controllers.iter_mut() {
-///         ac.current.reset();
+///         ac.reset();
+///     }
+/// }
+///
+/// // Controlling playback through systems
+/// fn hit_stop(mut anim_controllers: Query<&mut SpriteAnimController>) {
+///     for mut ac in anim_controllers.iter_mut() {
+///         ac.pause();
+///         ac.seek(2);
+///         ac.set_speed(1.5);
+///         ac.set_reversed(true);
+///         ac.resume();
 ///     }
 /// }
 /// ```
-#[derive(Component, Debug)]
+#[derive(Component, Reflect, Debug, Serialize, Deserialize)]
+#[reflect(Component, Debug, Default)]
 pub struct SpriteAnimController {
-    collection: HashMap<String, SpriteAnimData>,
-    pub current: SpriteAnimData,
+    library: ControllerLibrary,
+    current: SharedAnimData,
+    state: SpriteAnimState,
     timer: Timer,
     fps: f32,
+    speed: f32,
+    paused: bool,
+    reversed: bool,
+    frame_pending: bool,
+    /// The displayed frame changed without advancing, such as when swapping animations
+    #[reflect(ignore)]
+    #[serde(skip)]
+    redraw_pending: bool,
+    #[reflect(ignore)]
+    #[serde(skip)]
+    frame_changed: bool,
+    displayed: usize,
+    /// Restored controllers are validated again
+    #[reflect(ignore)]
+    #[serde(skip)]
+    validated: bool,
+    crossfade: Option<Duration>,
+    #[reflect(ignore)]
+    #[serde(skip)]
+    pending_crossfade: Option<Duration>,
+}
+
+/// Animation played by a `SpriteAnimController`, shared with its `SpriteAnimLibrary`.
+/// Saved as the name of the animation, which restored controllers look up in their library.
+#[derive(Reflect, Debug, Clone, Default)]
+#[reflect_value(Debug, Default, Serialize, Deserialize)]
+struct SharedAnimData(Arc<SpriteAnimData>);
+
+impl Serialize for SharedAnimData {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.name.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for SharedAnimData {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Ok(Self(Arc::new(SpriteAnimData::new(name, Vec::new()))))
+    }
+}
+
+impl Deref for SharedAnimData {
+    type Target = SpriteAnimData;
+
+    fn deref(&self) -> &SpriteAnimData {
+        &self.0
+    }
 }
 
 impl Default for SpriteAnimController {
@@ -170,83 +687,503 @@ impl Default for SpriteAnimController {
     /// The default frames per second is 4, but can be changed on instantiation using the
     /// `with_fps` function.
     fn default() -> Self {
-        let default = SpriteAnimData::default();
-        let mut collection = HashMap::new();
-        collection.insert("Default".to_string(), default.clone());
+        Self::from_library(&SpriteAnimLibrary::default())
+    }
+}
+
+impl SpriteAnimController {
+    /// Creates a new animation controller sharing the animations of `library`, and playing its
+    /// default animation. The default frames per second is 4.
+    pub fn from_library(library: &SpriteAnimLibrary) -> Self {
         let fps = 4.0;
         Self {
-            collection,
-            current: default,
+            library: ControllerLibrary::new(library.clone(), false),
+            current: SharedAnimData(library.default_anim()),
+            state: SpriteAnimState::default(),
             fps,
             timer: Timer::from_seconds(1.0 / fps, TimerMode::Repeating),
+            speed: 1.0,
+            paused: false,
+            reversed: false,
+            frame_pending: false,
+            redraw_pending: false,
+            frame_changed: false,
+            displayed: 0,
+            validated: false,
+            crossfade: None,
+            pending_crossfade: None,
         }
     }
-}
 
-impl SpriteAnimController {
     /// Sets the animation that will play by default if trying to play an animation that does not
     /// exist in the collection.
+    /// Stops sharing the animations of the library the controller was created from.
     pub fn with_default_anim(mut self, anim_data: SpriteAnimData) -> Self {
-        self.collection
-            .insert("Default".to_string(), anim_data);
+        self.library.set_default_anim(anim_data);
         self
     }
 
-    /// Adds an animation to the collection on instantiation
+    /// Adds an animation to the collection on instantiation.
+    /// An animation with the same name is replaced, which is reported as a
+    /// `SpriteAnimError::DuplicateName` once the controller is validated.
+    /// Stops sharing the animations of the library the controller was created from.
+    ///
+    /// Controllers of entities holding a `Handle<SpriteAnimLibrary>` are given the asset back on
+    /// every update, which drops the animations added this way. Add them to the asset instead.
     pub fn with_anim(mut self, anim_data: SpriteAnimData) -> Self {
-        self.collection
-            .insert(anim_data.name.clone(), anim_data);
+        self.library.replace_anim(anim_data);
+        self.validated = false;
+        self
+    }
+
+    /// Adds an animation to the collection.
+    /// Returns an error, without adding it, if it has no frames or if its name is already taken.
+    /// Stops sharing the animations of the library the controller was created from.
+    ///
+    /// Controllers of entities holding a `Handle<SpriteAnimLibrary>` are given the asset back on
+    /// every update, which drops the animations added this way. Add them to the asset instead.
+    pub fn insert_anim(&mut self, anim_data: SpriteAnimData) -> Result<(), SpriteAnimError> {
+        self.library.insert_anim(anim_data)?;
+        self.validated = false;
+        Ok(())
+    }
+
+    /// Checks every animation of the collection against `layout`, if given, and returns every
+    /// problem found: empty animations, frames out of range and duplicate names.
+    /// This is done automatically by the `SpriteAnimationPlugin` when the controller is added,
+    /// which only logs the problems.
+    pub fn validate(&self, layout: Option<&TextureAtlasLayout>) -> Vec<SpriteAnimError> {
+        self.library.validate(layout)
+    }
+
+    /// Returns the library holding the animations of the controller
+    pub fn library(&self) -> &SpriteAnimLibrary {
+        &self.library
+    }
+
+    /// Replaces the animations of the controller with those of `library`, and keeps playing the
+    /// current animation from where it was if it still exists. Falls back on the default
+    /// animation otherwise.
+    pub fn set_library(&mut self, library: SpriteAnimLibrary) {
+        self.replace_library(ControllerLibrary::new(library, false));
+    }
+
+    /// Same as `set_library`, for a library given by an asset of the entity
+    pub(crate) fn set_library_asset(&mut self, library: SpriteAnimLibrary) {
+        self.replace_library(ControllerLibrary::new(library, true));
+    }
+
+    /// Marks the library of the controller as given by an asset of the entity, so that it is
+    /// not saved along with the controller
+    pub(crate) fn shared_by_asset(mut self) -> Self {
+        self.library.from_asset = true;
+        self
+    }
+
+    /// Returns true if the controller was restored from a save, and waits for its entity to hold
+    /// its `Handle<SpriteAnimLibrary>`, or `Handle<SpriteAnimSheet>`, again. Restoring
+    /// controllers do not play until the asset is loaded.
+    pub fn is_restoring(&self) -> bool {
+        self.library.restoring && self.library.from_asset
+    }
+
+    fn replace_library(&mut self, library: ControllerLibrary) {
+        self.library = library;
+        self.validated = false;
+        let name = self.current.name.clone();
+        let len = self
+            .library
+            .get(name.as_str())
+            .map_or(0, |anim| anim.frames.len());
+        if self.displayed >= len || self.state.index >= len {
+            self.restart(name).ok();
+            return;
+        }
+        self.current = SharedAnimData(
+            self.library
+                .shared(&name)
+                .expect("Animation should exist"),
+        );
+        let duration = self.frame_duration(self.displayed);
+        self.timer.set_duration(duration);
+        self.redraw_pending = true;
+    }
+
+    /// Returns the animation currently playing
+    pub fn current(&self) -> &SpriteAnimData {
+        &self.current
+    }
+
+    /// Returns the playback state of the current animation
+    pub fn state(&self) -> &SpriteAnimState {
+        &self.state
+    }
+
+    /// Returns true once the current animation has finished, see `SpriteAnimState::is_finished`
+    pub fn is_finished(&self) -> bool {
+        self.state.finished
+    }
+
+    /// Sets the index of the current animation to zero and effectively starts it over
+    pub fn reset(&mut self) {
+        self.state.reset();
+    }
+
+    /// Makes `play` crossfade between animations over `duration`, instead of cutting directly
+    /// from one to the other.
+    pub fn with_crossfade(mut self, duration: Duration) -> Self {
+        self.crossfade = Some(duration);
         self
     }
 
     /// Sets framerate on instantiation
     pub fn with_fps(mut self, fps: f32) -> Self {
-        self.fps = fps;
+        self.set_fps(fps);
         self
     }
 
-    /// Sets the framerate of the controller
+    /// Sets the framerate of the controller.
+    /// Animations that have their own framerate, or frame durations, are not affected.
+    /// Framerates that are not strictly positive are ignored.
     pub fn set_fps(&mut self, fps: f32) {
+        if fps <= 0.0 || fps.is_nan() {
+            warn!("Ignoring the invalid framerate {}", fps);
+            return;
+        }
         self.fps = fps;
-        self.timer
-            .set_duration(Duration::from_secs_f32(1.0 / self.fps));
+        let duration = self.frame_duration(self.state.index);
+        self.timer.set_duration(duration);
     }
 
-    /// Sets the current animation to be played.
-    /// The animation must be part of the collection owned by the `SpriteAnimController`.
-    pub fn set_current_animation(
+    /// Returns the framerate of the controller
+    pub fn fps(&self) -> f32 {
+        self.fps
+    }
+
+    /// Returns true if the animation is part of the collection
+    pub fn has_animation(&self, anim: impl SpriteAnimKey) -> bool {
+        self.library.has_animation(anim)
+    }
+
+    /// Returns the index (position in the `frames` vector) of the frame of the current animation
+    /// being displayed
+    pub fn frame_index(&self) -> usize {
+        self.displayed
+    }
+
+    /// Returns the frame (value of the `frames` vector) of the current animation being displayed
+    pub fn frame(&self) -> usize {
+        self.current
+            .frames
+            .get(self.displayed)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Pauses the current animation on the frame it is displaying
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Resumes the current animation where it was paused
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    /// Returns true if the controller is paused
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Sets the playback speed multiplier, applied on top of the framerate.
+    /// A speed of 2.0 plays animations twice as fast. Negative speeds are treated as 0.0, use
+    /// `set_reversed` to play animations backwards.
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed.max(0.0);
+    }
+
+    /// Returns the playback speed multiplier
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    /// Plays animations backwards when `reversed` is true.
+    /// `SpriteAnimMode::Once` and `SpriteAnimMode::Loop` animations then finish on their first
+    /// frame, `SpriteAnimMode::MirrorOnce` animations bounce on their first frame and finish on
+    /// their last one, and `SpriteAnimMode::LoopFrom` animations loop back to their last frame.
+    pub fn set_reversed(&mut self, reversed: bool) {
+        if self.reversed != reversed {
+            self.state.forward = !self.state.forward;
+        }
+        self.reversed = reversed;
+    }
+
+    /// Returns true if animations are played backwards
+    pub fn is_reversed(&self) -> bool {
+        self.reversed
+    }
+
+    /// Jumps to the frame at `index` (position in the `frames` vector) of the current animation.
+    /// The frame is displayed on the next update, even if the controller is paused.
+    pub fn seek(&mut self, index: usize) {
+        self.state.index = index.min(self.current.frames.len().saturating_sub(1));
+        self.state.finished = false;
+        self.frame_pending = true;
+        self.timer.reset();
+    }
+
+    /// Returns how long the frame at `index` of the current animation should be displayed.
+    /// Falls back on the framerate of the controller if the animation does not override it.
+    pub fn frame_duration(&self, index: usize) -> Duration {
+        self.current
+            .frame_duration(index)
+            .unwrap_or_else(|| Duration::from_secs_f32(1.0 / self.fps))
+    }
+
+    /// Replaces the animations and framerate of the controller with those of `other`, and keeps
+    /// playing the current animation from where it was if it still exists. Falls back on the
+    /// default animation otherwise.
+    pub(crate) fn reload(&mut self, other: SpriteAnimController) {
+        self.fps = other.fps;
+        self.set_library_asset(other.library().clone());
+    }
+
+    /// Restarts the timer using the timing of the first frame of the current animation, and
+    /// starts reversed animations from their last frame.
+    fn retime(&mut self) {
+        if self.reversed && self.current.mode != SpriteAnimMode::Mirror {
+            self.state.index = self.current.frames.len().saturating_sub(1);
+            if self.current.mode == SpriteAnimMode::MirrorOnce {
+                self.state.forward = false;
+            }
+        }
+        let duration = self.frame_duration(self.state.index);
+        self.timer.set_duration(duration);
+        self.timer.reset();
+        self.displayed = self.state.index;
+        self.frame_pending = true;
+    }
+
+    /// Plays an animation, keeping its progress if it is already the current animation.
+    /// Safe to call every frame, as opposed to `restart`.
+    ///
+    /// Returns `SpriteAnimError::AlreadyPlaying` if the animation was already playing, which can
+    /// usually be ignored. If the animation does not exist, the default animation is played
+    /// instead and `SpriteAnimError::DoesNotExist` is returned.
+    pub fn play(&mut self, anim: impl SpriteAnimKey) -> Result<(), SpriteAnimError> {
+        let crossfade = self.crossfade;
+        self.play_with_crossfade(anim.anim_name(), crossfade)
+    }
+
+    /// Same as `play`, but fades the previous animation out over `duration`.
+    /// Requires the entity to have a `Sprite` and a `Handle<Image>`.
+    ///
+    /// Example usage:
+    /// ```Rust
+    /// fn stop_running(mut anim_controllers: Query<&mut SpriteAnimController>) {
+    ///     for mut ac in anim_controllers.iter_mut() {
+    ///         ac.crossfade("Idle", Duration::from_millis(150)).ok();
+    ///     }
+    /// }
+    /// ```
+    pub fn crossfade(
+        &mut self,
+        anim: impl SpriteAnimKey,
+        duration: Duration,
+    ) -> Result<(), SpriteAnimError> {
+        self.play_with_crossfade(anim.anim_name(), Some(duration))
+    }
+
+    fn play_with_crossfade(
         &mut self,
-        anim_name: impl Into<String>,
+        anim_name: &str,
+        crossfade: Option<Duration>,
     ) -> Result<(), SpriteAnimError> {
-        let anim_name: String = anim_name.into();
-        if !self.collection.contains_key(&anim_name) {
-            self.current = self
-                .collection
-                .get("Default")
-                .expect("Default should exist")
-                .clone();
-            return Err(SpriteAnimError::DoesNotExist(anim_name));
+        // Missing animations resolve to the default animation, like in `restart`
+        let exists = self.library.has_animation(anim_name);
+        let target = self
+            .library
+            .get(anim_name)
+            .or_else(|| self.library.get("Default"))
+            .expect("Default should exist");
+        if target.name == self.current.name {
+            if !exists {
+                return Err(SpriteAnimError::DoesNotExist(anim_name.to_string()));
+            }
+            return Err(SpriteAnimError::AlreadyPlaying(anim_name.to_string()));
         }
-        self.current = self
-            .collection
-            .get(&anim_name)
-            .expect("Animation should exist")
-            .clone();
+        let result = self.restart(anim_name);
+        self.pending_crossfade = crossfade.filter(|duration| !duration.is_zero());
+        result
+    }
+
+    /// Plays an animation from its first frame, even if it is already the current animation.
+    /// The animation must be part of the library of the `SpriteAnimController`.
+    /// Never crossfades.
+    pub fn restart(&mut self, anim: impl SpriteAnimKey) -> Result<(), SpriteAnimError> {
+        let anim_name = anim.anim_name();
+        let (current, result) = match self.library.shared(anim_name) {
+            Some(anim) => (anim, Ok(())),
+            None => (
+                self.library.default_anim(),
+                Err(SpriteAnimError::DoesNotExist(anim_name.to_string())),
+            ),
+        };
+        self.current = SharedAnimData(current);
+        self.state = SpriteAnimState::default();
+        self.retime();
+        result
+    }
+
+    /// Replaces the current animation with `anim`, such as one of its `FacingVariants`, keeping
+    /// the displayed frame, the progress of the animation and the time already spent on the frame.
+    pub(crate) fn swap_animation(&mut self, anim: &str) -> Result<(), SpriteAnimError> {
+        let anim = self
+            .library
+            .shared(anim)
+            .ok_or_else(|| SpriteAnimError::DoesNotExist(anim.to_string()))?;
+        self.current = SharedAnimData(anim);
+        let duration = self.frame_duration(self.displayed);
+        self.timer.set_duration(duration);
+        self.redraw_pending = true;
         Ok(())
     }
+
+    /// Sets the current animation to be played. Same as `play`, except that the animation
+    /// already playing is not an error, so it can be called every frame.
+    pub fn set_current_animation(
+        &mut self,
+        anim: impl SpriteAnimKey,
+    ) -> Result<(), SpriteAnimError> {
+        match self.play(anim) {
+            Err(SpriteAnimError::AlreadyPlaying(_)) => Ok(()),
+            result => result,
+        }
+    }
+}
+
+/// System that looks up the current animation of controllers restored from a save in their
+/// library. Controllers using a library asset are restored once their entity holds it again, by
+/// `apply_sprite_anim_libraries` or `apply_sprite_anim_sheets`.
+fn restore_controllers(mut query: Query<&mut SpriteAnimController>) {
+    for mut ac in query.iter_mut() {
+        if ac.library.restoring && !ac.library.from_asset {
+            let library = ac.library().clone();
+            ac.set_library(library);
+        }
+    }
+}
+
+/// System that validates new or modified `SpriteAnimController`s against the layout of their
+/// `TextureAtlas`, and logs the problems found, once per entity. Controllers are only validated
+/// once their layout is loaded.
+///
+/// Use `SpriteAnimController::validate` to handle the problems as `SpriteAnimError`s instead.
+fn validate_controllers(
+    mut query: Query<(Entity, &mut SpriteAnimController, Option<&TextureAtlas>)>,
+    layouts: Res<Assets<TextureAtlasLayout>>,
+) {
+    for (entity, mut ac, ta) in query.iter_mut() {
+        if ac.validated || ac.is_restoring() {
+            continue;
+        }
+        let layout = match ta {
+            Some(ta) => match layouts.get(&ta.layout) {
+                Some(layout) => Some(layout),
+                None => continue,
+            },
+            None => None,
+        };
+        let errors: Vec<String> = ac
+            .validate(layout)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        if !errors.is_empty() {
+            error!("Invalid animations on {}: {}", entity, errors.join("; "));
+        }
+        ac.validated = true;
+    }
 }
 
-/// System that plays the animation inside the `current` field of the `SpriteAnimController`
+/// Time used to advance animations, depending on the `SpriteAnimClock` of the plugin
+#[derive(SystemParam)]
+struct SpriteAnimTime<'w> {
+    config: Res<'w, SpriteAnimConfig>,
+    time: Res<'w, Time>,
+    virtual_time: Res<'w, Time<Virtual>>,
+    real_time: Res<'w, Time<Real>>,
+}
+
+impl SpriteAnimTime<'_> {
+    fn delta(&self) -> Duration {
+        match self.config.clock {
+            SpriteAnimClock::Default => self.time.delta(),
+            SpriteAnimClock::Virtual => self.virtual_time.delta(),
+            SpriteAnimClock::Real => self.real_time.delta(),
+        }
+    }
+}
+
+/// System that plays the current animation of every `SpriteAnimController` and sends the marker
+/// and finished events of the frames it displays.
+/// Paused controllers only display the frames they seek to.
+///
+/// The frames are displayed by the `SpriteAnimTarget`s of the entity, which run right after.
 fn play_animation(
-    mut query: Query<(&mut SpriteAnimController, &mut TextureAtlas)>,
-    time: Res<Time>,
+    mut query: Query<(Entity, &mut SpriteAnimController)>,
+    mut marker_events: EventWriter<SpriteAnimMarkerEvent>,
+    mut finished_events: EventWriter<SpriteAnimFinishedEvent>,
+    time: SpriteAnimTime,
 ) {
-    for (mut ac, mut ta) in query.iter_mut() {
-        ac.timer.tick(time.delta());
-        if ac.timer.just_finished() {
-            match ac.current.get_frame() {
-                Ok(frame) => {
-                    ta.index = *frame;
+    for (entity, mut ac) in query.iter_mut() {
+        if ac.is_restoring() {
+            continue;
+        }
+        if ac.frame_changed || ac.redraw_pending {
+            ac.frame_changed = ac.redraw_pending;
+            ac.redraw_pending = false;
+        }
+        // A pending frame is displayed right away, and its duration starts from now
+        let advance = if ac.frame_pending {
+            ac.frame_pending = false;
+            true
+        } else if !ac.paused {
+            let delta = time.delta().mul_f32(ac.speed);
+            ac.timer.tick(delta).just_finished()
+        } else {
+            false
+        };
+        if advance {
+            let ac = &mut *ac;
+            let index = ac.state.index;
+            let was_finished = ac.state.finished;
+            match ac.current.get_frame(&mut ac.state, ac.reversed) {
+                Ok(_) => {
+                    ac.frame_changed = true;
+                    ac.displayed = index;
+                    // The displayed frame stays on screen for its own duration
+                    let duration = ac.frame_duration(index);
+                    ac.timer.set_duration(duration);
+
+                    // A finished animation keeps displaying its last frame, which should not
+                    // trigger its events again
+                    if was_finished {
+                        continue;
+                    }
+                    for marker in ac.current.markers(index) {
+                        marker_events.send(SpriteAnimMarkerEvent {
+                            entity,
+                            animation: ac.current.name.clone(),
+                            marker: marker.clone(),
+                        });
+                    }
+                    if ac.state.finished {
+                        finished_events.send(SpriteAnimFinishedEvent {
+                            entity,
+                            animation: ac.current.name.clone(),
+                        });
+                    }
                 }
                 Err(err) => {
                     error!("{}", err)
@@ -300,12 +1237,747 @@ pub struct AnimatedSpriteBundle {
     pub texture_atlas: TextureAtlas,
 }
 
+/// Bundle of components required to display an animated UI image using the
+/// `SpriteAnimController`, such as HUD icons.
+///
+/// Example usage:
+/// ```Rust
+/// commands.spawn(AnimatedImageBundle {
+///     image_bundle: ImageBundle {
+///         image: UiImage::new(asset_server.load("icons.png")),
+///         style: Style {
+///             width: Val::Px(32.0),
+///             height: Val::Px(32.0),
+///             ..default()
+///         },
+///         ..default()
+///     },
+///     controller,
+///     texture_atlas: TextureAtlas::from(icons_layout_handle),
+/// });
+/// ```
+#[derive(Bundle)]
+pub struct AnimatedImageBundle {
+    pub image_bundle: ImageBundle,
+    pub controller: SpriteAnimController,
+    pub texture_atlas: TextureAtlas,
+}
+
+/// System sets of the `SpriteAnimationPlugin`, running in this order in the schedule of the
+/// plugin.
+///
+/// Example usage:
+/// ```Rust
+/// // Animations switched by `movement` are displayed on the same frame
+/// app.add_systems(Update, movement.before(SpriteAnimSet::Prepare));
+/// ```
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SpriteAnimSet {
+    /// Sets up controllers, and picks their animation from their `SpriteAnimGraph` and `Facing`
+    Prepare,
+    /// Advances animations and sends their events
+    Play,
+    /// Displays the frames played on the targets, layers and crossfades
+    Apply,
+}
+
+/// Time advancing animations
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SpriteAnimClock {
+    /// `Time` of the schedule the plugin runs in, such as `Time<Fixed>` in `FixedUpdate`
+    #[default]
+    Default,
+    /// `Time<Virtual>`, which stops when the game is paused
+    Virtual,
+    /// `Time<Real>`, which keeps going when the game is paused, such as for menus
+    Real,
+}
+
+/// Settings of the `SpriteAnimationPlugin`
+#[derive(Resource, Debug, Clone)]
+struct SpriteAnimConfig {
+    schedule: InternedScheduleLabel,
+    clock: SpriteAnimClock,
+}
+
 /// Plugin that adds sprite animation controls to a sprite.
-/// To use, the easiest way is to spawn an `AnimatedSpriteBundle`.
-pub struct SpriteAnimationPlugin;
+/// To use, the easiest way is to spawn an `AnimatedSpriteBundle`, or a `SpriteAnimSheetBundle` to
+/// load the animations from a file. UI images are animated the same way, using an
+/// `AnimatedImageBundle`.
+///
+/// Runs in `Update` by default, see `SpriteAnimSet` to order systems around animations.
+///
+/// Example usage:
+/// ```Rust
+/// app.add_plugins(
+///     SpriteAnimationPlugin::default()
+///         .in_schedule(PostUpdate)
+///         .with_clock(SpriteAnimClock::Real),
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct SpriteAnimationPlugin {
+    schedule: InternedScheduleLabel,
+    clock: SpriteAnimClock,
+}
+
+impl Default for SpriteAnimationPlugin {
+    fn default() -> Self {
+        Self {
+            schedule: Update.intern(),
+            clock: SpriteAnimClock::Default,
+        }
+    }
+}
+
+impl SpriteAnimationPlugin {
+    /// Runs the animation systems in `schedule` instead of `Update`
+    pub fn in_schedule(mut self, schedule: impl ScheduleLabel) -> Self {
+        self.schedule = schedule.intern();
+        self
+    }
+
+    /// Advances animations using `clock`
+    pub fn with_clock(mut self, clock: SpriteAnimClock) -> Self {
+        self.clock = clock;
+        self
+    }
+}
 
 impl Plugin for SpriteAnimationPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, play_animation);
+        app.insert_resource(SpriteAnimConfig {
+            schedule: self.schedule,
+            clock: self.clock,
+        })
+        .configure_sets(
+            self.schedule,
+            (
+                SpriteAnimSet::Prepare,
+                SpriteAnimSet::Play,
+                SpriteAnimSet::Apply,
+            )
+                .chain(),
+        )
+        .add_event::<SpriteAnimMarkerEvent>()
+        .add_event::<SpriteAnimFinishedEvent>()
+        .init_asset::<SpriteAnimSheet>()
+        .init_asset::<SpriteAnimLibrary>()
+        .init_asset_loader::<SpriteAnimSheetLoader>()
+        .init_asset_loader::<AsepriteLoader>()
+        .register_type::<SpriteAnimController>()
+        .register_type::<SpriteAnimData>()
+        .register_type::<SpriteAnimMode>()
+        .register_type::<SpriteAnimState>()
+        .register_type::<SharedAnimData>()
+        .register_type::<ControllerLibrary>()
+        .register_type::<SpriteAnimLibrary>()
+        .add_systems(
+            self.schedule,
+            (
+                (
+                    sheet::apply_sprite_anim_sheets,
+                    library::apply_sprite_anim_libraries,
+                    restore_controllers,
+                    validate_controllers,
+                    graph::update_anim_graphs,
+                    facing::apply_facing,
+                    crossfade::start_crossfades,
+                )
+                    .chain()
+                    .in_set(SpriteAnimSet::Prepare),
+                play_animation.in_set(SpriteAnimSet::Play),
+                (
+                    target::apply_sprite_anim_target::<TextureAtlas>,
+                    target::apply_sprite_anim_images,
+                    layer::sync_anim_layers,
+                    crossfade::update_crossfades,
+                )
+                    .chain()
+                    .in_set(SpriteAnimSet::Apply),
+            ),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        ecs::entity::EntityHashMap,
+        scene::{serde::SceneDeserializer, DynamicSceneBuilder},
+    };
+    use serde::de::DeserializeSeed;
+
+    use super::*;
+    use crate::test_utils::{TestApp, STEP};
+
+    const FRAMES: [usize; 4] = [10, 11, 12, 13];
+
+    /// Creates a controller playing an animation of `FRAMES` using `mode`
+    fn controller(mode: SpriteAnimMode) -> SpriteAnimController {
+        let anim = SpriteAnimData::new("Anim", FRAMES.to_vec()).with_mode(mode);
+        let mut controller = SpriteAnimController::default().with_anim(anim);
+        controller.play("Anim").unwrap();
+        controller
+    }
+
+    /// Returns the first `count` frames played by a controller using `mode`
+    fn sequence(mode: SpriteAnimMode, count: usize) -> Vec<usize> {
+        let mut app = TestApp::new();
+        let entity = app.spawn(controller(mode));
+        app.frames(entity, count)
+    }
+
+    #[test]
+    fn get_frame_mirror_bounces_on_both_ends() {
+        let anim = SpriteAnimData::new("Anim", FRAMES.to_vec()).with_mode(SpriteAnimMode::Mirror);
+        let mut state = SpriteAnimState::default();
+        let indices: Vec<usize> = (0..8)
+            .map(|_| {
+                let frame = anim.get_frame(&mut state, false).unwrap();
+                assert!(state.index < FRAMES.len());
+                frame
+            })
+            .collect();
+        assert_eq!(indices, vec![10, 11, 12, 13, 12, 11, 10, 11]);
+    }
+
+    #[test]
+    fn get_frame_single_frame() {
+        let anim = SpriteAnimData::new("Anim", vec![7]).with_mode(SpriteAnimMode::Mirror);
+        let mut state = SpriteAnimState::default();
+        assert_eq!(anim.get_frame(&mut state, false).unwrap(), 7);
+        assert_eq!(anim.get_frame(&mut state, true).unwrap(), 7);
+        assert_eq!(state.index, 0);
+    }
+
+    #[test]
+    fn get_frame_no_frames() {
+        let anim = SpriteAnimData::new("Anim", vec![]);
+        assert!(matches!(
+            anim.get_frame(&mut SpriteAnimState::default(), false),
+            Err(SpriteAnimError::NoFrames(_))
+        ));
+    }
+
+    #[test]
+    fn validate_reports_every_problem() {
+        let controller = SpriteAnimController::default()
+            .with_anim(SpriteAnimData::new("Idle", vec![0, 1]))
+            .with_anim(SpriteAnimData::new("Idle", vec![0, 1, 2]))
+            .with_anim(SpriteAnimData::new("Run", vec![2, 3, 4]))
+            .with_anim(SpriteAnimData::new("Empty", vec![]));
+        let layout = TextureAtlasLayout::from_grid(UVec2::splat(8), 4, 1, None, None);
+
+        let errors = controller.validate(Some(&layout));
+        assert_eq!(errors.len(), 3);
+        assert!(errors
+            .iter()
+            .any(|err| matches!(err, SpriteAnimError::DuplicateName(name) if name == "Idle")));
+        assert!(errors.iter().any(
+            |err| matches!(err, SpriteAnimError::FrameOutOfRange(name, 4, 4) if name == "Run")
+        ));
+        assert!(errors
+            .iter()
+            .any(|err| matches!(err, SpriteAnimError::NoFrames(name) if name == "Empty")));
+
+        // Without a layout, frames cannot be out of range
+        assert_eq!(controller.validate(None).len(), 2);
+    }
+
+    #[test]
+    fn insert_anim_rejects_invalid_animations() {
+        let mut controller = SpriteAnimController::default();
+        assert!(controller
+            .insert_anim(SpriteAnimData::new("Idle", vec![0]))
+            .is_ok());
+        assert!(matches!(
+            controller.insert_anim(SpriteAnimData::new("Idle", vec![1])),
+            Err(SpriteAnimError::DuplicateName(_))
+        ));
+        assert!(matches!(
+            controller.insert_anim(SpriteAnimData::new("Empty", vec![])),
+            Err(SpriteAnimError::NoFrames(_))
+        ));
+        assert!(!controller.has_animation("Empty"));
+    }
+
+    #[test]
+    fn frame_helpers() {
+        assert_eq!(
+            SpriteAnimData::from_range("Anim", 2..5).frames,
+            vec![2, 3, 4]
+        );
+        assert_eq!(
+            SpriteAnimData::from_grid_row("Anim", 4, 2).frames,
+            vec![8, 9, 10, 11]
+        );
+        assert_eq!(
+            SpriteAnimData::from_grid("Anim", 7, 1, 5, 4).frames,
+            vec![12, 13, 14, 15]
+        );
+
+        let anim = SpriteAnimData::from_range("Anim", 0..3)
+            .with_marker(0, "start")
+            .with_frame_duration(2, Duration::from_secs(1))
+            .concat(SpriteAnimData::from_range("Other", 5..7).with_marker(1, "end"))
+            .reversed();
+        assert_eq!(anim.name, "Anim");
+        assert_eq!(anim.frames, vec![6, 5, 2, 1, 0]);
+        assert_eq!(anim.markers(0), ["end"]);
+        assert_eq!(anim.markers(4), ["start"]);
+        assert_eq!(anim.frame_duration(2), Some(Duration::from_secs(1)));
+
+        let anim = SpriteAnimData::from_range("Anim", 0..2)
+            .with_frame_duration(3, Duration::from_secs(1))
+            .with_marker(2, "past_end")
+            .with_marker(1, "end")
+            .reversed();
+        assert_eq!(anim.frames, vec![1, 0]);
+        assert_eq!(anim.markers(0), ["end"]);
+        assert!(anim.markers(1).is_empty());
+        assert_eq!(anim.frame_duration(0), None);
+        assert_eq!(anim.frame_duration(1), None);
+    }
+
+    #[test]
+    fn from_atlas_textures() {
+        let mut images = Assets::<Image>::default();
+        let lamp = images.add(Image::default());
+        let sign = images.add(Image::default());
+        let fence = images.add(Image::default());
+
+        let mut builder = TextureAtlasBuilder::default();
+        builder.add_texture(Some(lamp.id()), images.get(&lamp).unwrap());
+        builder.add_texture(Some(sign.id()), images.get(&sign).unwrap());
+        let (layout, _) = builder.build().unwrap();
+
+        let anim = SpriteAnimData::from_atlas_textures("Anim", &layout, [&sign, &lamp]).unwrap();
+        assert_eq!(
+            anim.frames,
+            vec![
+                layout.get_texture_index(&sign).unwrap(),
+                layout.get_texture_index(&lamp).unwrap()
+            ]
+        );
+        assert!(matches!(
+            SpriteAnimData::from_atlas_textures("Anim", &layout, [&fence]),
+            Err(SpriteAnimError::TextureNotInLayout(_, _))
+        ));
+    }
+
+    #[test]
+    fn repeating() {
+        assert_eq!(
+            sequence(SpriteAnimMode::Repeating, 6),
+            vec![10, 11, 12, 13, 10, 11]
+        );
+    }
+
+    #[test]
+    fn once() {
+        assert_eq!(
+            sequence(SpriteAnimMode::Once, 6),
+            vec![10, 11, 12, 13, 13, 13]
+        );
+    }
+
+    #[test]
+    fn mirror() {
+        assert_eq!(
+            sequence(SpriteAnimMode::Mirror, 9),
+            vec![10, 11, 12, 13, 12, 11, 10, 11, 12]
+        );
+    }
+
+    #[test]
+    fn mirror_once() {
+        assert_eq!(
+            sequence(SpriteAnimMode::MirrorOnce, 9),
+            vec![10, 11, 12, 13, 12, 11, 10, 10, 10]
+        );
+    }
+
+    #[test]
+    fn loop_count() {
+        assert_eq!(
+            sequence(SpriteAnimMode::Loop(2), 10),
+            vec![10, 11, 12, 13, 10, 11, 12, 13, 13, 13]
+        );
+    }
+
+    #[test]
+    fn loop_from() {
+        assert_eq!(
+            sequence(SpriteAnimMode::LoopFrom(2), 8),
+            vec![10, 11, 12, 13, 12, 13, 12, 13]
+        );
+    }
+
+    #[test]
+    fn random() {
+        let frames = sequence(SpriteAnimMode::Random, 50);
+        assert!(frames.iter().all(|frame| FRAMES.contains(frame)));
+        assert!(frames.windows(2).all(|pair| pair[0] != pair[1]));
+    }
+
+    #[test]
+    fn reversed() {
+        let mut app = TestApp::new();
+        let mut controller = controller(SpriteAnimMode::Once);
+        controller.set_reversed(true);
+        controller.restart("Anim").unwrap();
+        let entity = app.spawn(controller);
+        assert_eq!(app.frames(entity, 6), vec![13, 12, 11, 10, 10, 10]);
+    }
+
+    #[test]
+    fn reversed_mirror_once() {
+        let mut app = TestApp::new();
+        let mut controller = controller(SpriteAnimMode::MirrorOnce);
+        controller.set_reversed(true);
+        controller.restart("Anim").unwrap();
+        let entity = app.spawn(controller);
+        assert_eq!(
+            app.frames(entity, 9),
+            vec![13, 12, 11, 10, 11, 12, 13, 13, 13]
+        );
+        assert!(app.controller_mut(entity).is_finished());
+    }
+
+    #[test]
+    fn frame_duration_overrides_fps() {
+        let anim = SpriteAnimData::new("Anim", FRAMES.to_vec())
+            .with_fps(2.0)
+            .with_frame_duration(1, Duration::from_secs(1));
+        let mut controller = SpriteAnimController::default().with_anim(anim);
+        controller.play("Anim").unwrap();
+
+        let mut app = TestApp::new();
+        let entity = app.spawn(controller);
+        assert_eq!(
+            app.frames(entity, 10),
+            vec![10, 10, 11, 11, 11, 11, 12, 12, 13, 13]
+        );
+    }
+
+    #[test]
+    fn invalid_fps_is_ignored() {
+        let anim = SpriteAnimData::new("Anim", FRAMES.to_vec()).with_fps(0.0);
+        assert_eq!(anim.fps, None);
+
+        let mut controller = SpriteAnimController::default()
+            .with_anim(anim)
+            .with_fps(4.0);
+        controller.set_fps(-1.0);
+        controller.set_fps(f32::NAN);
+        assert_eq!(controller.fps(), 4.0);
+    }
+
+    #[test]
+    fn pause_speed_and_seek() {
+        let mut app = TestApp::new();
+        let entity = app.spawn(controller(SpriteAnimMode::Repeating));
+        assert_eq!(app.frames(entity, 2), vec![10, 11]);
+
+        app.controller_mut(entity).pause();
+        assert_eq!(app.frames(entity, 2), vec![11, 11]);
+
+        app.controller_mut(entity).seek(3);
+        assert_eq!(app.frames(entity, 2), vec![13, 13]);
+
+        let mut ac = app.controller_mut(entity);
+        ac.resume();
+        ac.set_speed(0.5);
+        assert_eq!(app.frames(entity, 4), vec![13, 10, 10, 11]);
+    }
+
+    #[test]
+    fn play_resolves_the_default_animation() {
+        let mut controller = SpriteAnimController::default()
+            .with_anim(SpriteAnimData::new("Idle", FRAMES.to_vec()))
+            .with_anim(SpriteAnimData::new("Run", FRAMES.to_vec()))
+            .with_default_anim(SpriteAnimData::new("Idle", FRAMES.to_vec()));
+        controller.restart("Idle").unwrap();
+        assert!(matches!(
+            controller.play("Default"),
+            Err(SpriteAnimError::AlreadyPlaying(_))
+        ));
+        assert!(matches!(
+            controller.play("Jump"),
+            Err(SpriteAnimError::DoesNotExist(_))
+        ));
+
+        controller.play("Run").unwrap();
+        controller.seek(2);
+        controller.set_current_animation("Run").unwrap();
+        assert_eq!(controller.state().index(), 2);
+        controller.play("Default").unwrap();
+        assert_eq!(controller.current().name(), "Idle");
+    }
+
+    #[test]
+    fn play_keeps_progress() {
+        let mut app = TestApp::new();
+        let entity = app.spawn(controller(SpriteAnimMode::Repeating));
+        app.frames(entity, 2);
+        assert!(matches!(
+            app.controller_mut(entity).play("Anim"),
+            Err(SpriteAnimError::AlreadyPlaying(_))
+        ));
+        assert_eq!(app.frames(entity, 1), vec![12]);
+
+        app.controller_mut(entity)
+            .restart("Anim")
+            .unwrap();
+        assert_eq!(app.frames(entity, 1), vec![10]);
+    }
+
+    #[test]
+    fn marker_and_finished_events() {
+        let anim = SpriteAnimData::new("Anim", FRAMES.to_vec())
+            .with_mode(SpriteAnimMode::Once)
+            .with_marker(2, "hit");
+        let mut controller = SpriteAnimController::default().with_anim(anim);
+        controller.play("Anim").unwrap();
+
+        let mut app = TestApp::new();
+        let entity = app.spawn(controller);
+        app.frames(entity, 2);
+        assert!(app.events::<SpriteAnimMarkerEvent>().is_empty());
+
+        app.step();
+        let markers = app.events::<SpriteAnimMarkerEvent>();
+        assert_eq!(markers.len(), 1);
+        assert_eq!(markers[0].entity, entity);
+        assert_eq!(markers[0].marker, "hit");
+        assert!(app.events::<SpriteAnimFinishedEvent>().is_empty());
+
+        app.step();
+        assert!(app.events::<SpriteAnimMarkerEvent>().is_empty());
+        let finished = app.events::<SpriteAnimFinishedEvent>();
+        assert_eq!(finished.len(), 1);
+        assert_eq!(finished[0].animation, "Anim");
+
+        // Holding the last frame does not send the events again
+        app.step();
+        assert!(app.events::<SpriteAnimFinishedEvent>().is_empty());
+    }
+
+    #[test]
+    fn crossfade_spawns_fading_sprite() {
+        let controller = controller(SpriteAnimMode::Repeating)
+            .with_anim(SpriteAnimData::new("Other", vec![20, 21]));
+        let mut app = TestApp::new();
+        let entity = app.spawn(controller);
+        app.app
+            .world_mut()
+            .entity_mut(entity)
+            .insert((Sprite::default(), Handle::<Image>::default()));
+        app.frames(entity, 1);
+
+        app.controller_mut(entity)
+            .crossfade("Other", STEP * 2)
+            .unwrap();
+        assert_eq!(app.frames(entity, 1), vec![20]);
+        let mut query = app
+            .app
+            .world_mut()
+            .query::<(&Parent, &TextureAtlas, &Sprite, &SpriteAnimCrossfade)>();
+        let (parent, ta, sprite, _) = query.single(app.app.world());
+        assert_eq!(parent.get(), entity);
+        assert_eq!(ta.index, 10);
+        assert!(sprite.color.alpha() < 1.0);
+
+        app.step();
+        assert!(query.iter(app.app.world()).next().is_none());
+    }
+
+    /// Spawns an entity with a sprite, playing "Anim" then crossfading to "Other" over
+    /// `duration`
+    fn spawn_crossfading(app: &mut TestApp, duration: Duration) -> Entity {
+        let controller = controller(SpriteAnimMode::Repeating)
+            .with_anim(SpriteAnimData::new("Other", vec![20, 21]));
+        let entity = app.spawn(controller);
+        app.app
+            .world_mut()
+            .entity_mut(entity)
+            .insert((Sprite::default(), Handle::<Image>::default()));
+        app.frames(entity, 1);
+        app.controller_mut(entity)
+            .crossfade("Other", duration)
+            .unwrap();
+        entity
+    }
+
+    #[test]
+    fn crossfade_shorter_than_a_frame() {
+        let mut app = TestApp::new();
+        let entity = spawn_crossfading(&mut app, STEP / 2);
+
+        // The fade is over before it is ever drawn, so it cuts to the new animation
+        assert_eq!(app.frames(entity, 2), vec![20, 21]);
+        let mut query = app
+            .app
+            .world_mut()
+            .query::<&SpriteAnimCrossfade>();
+        assert!(query.iter(app.app.world()).next().is_none());
+    }
+
+    #[test]
+    fn crossfade_follows_flip() {
+        let mut app = TestApp::new();
+        let entity = spawn_crossfading(&mut app, STEP * 3);
+        app.step();
+        app.app
+            .world_mut()
+            .get_mut::<Sprite>(entity)
+            .unwrap()
+            .flip_x = true;
+        app.step();
+
+        let mut query = app
+            .app
+            .world_mut()
+            .query_filtered::<&Sprite, With<SpriteAnimCrossfade>>();
+        assert!(query.single(app.app.world()).flip_x);
+    }
+
+    #[test]
+    fn custom_schedule_and_clock() {
+        let plugin = SpriteAnimationPlugin::default()
+            .in_schedule(PostUpdate)
+            .with_clock(SpriteAnimClock::Real);
+        let mut app = TestApp::with_plugin(plugin);
+        let entity = app.spawn(controller(SpriteAnimMode::Repeating));
+        app.app
+            .world_mut()
+            .resource_mut::<Time<Virtual>>()
+            .pause();
+
+        // Animations keep playing while the game is paused
+        assert_eq!(app.frames(entity, 2), vec![10, 11]);
+
+        // Frames sought in `Update` are displayed on the same update
+        app.app
+            .add_systems(Update, |mut query: Query<&mut SpriteAnimController>| {
+                for mut ac in query.iter_mut() {
+                    ac.seek(3);
+                }
+            });
+        assert_eq!(app.frames(entity, 1), vec![13]);
+    }
+
+    /// Saves the controller of `entity` to a scene, and loads it into a new `TestApp`.
+    /// Returns the new app, the restored entity and the saved scene.
+    fn round_trip(app: &TestApp, entity: Entity) -> (TestApp, Entity, String) {
+        let registry = app
+            .app
+            .world()
+            .resource::<AppTypeRegistry>()
+            .clone();
+        let scene = DynamicSceneBuilder::from_world(app.app.world())
+            .deny_all()
+            .allow::<SpriteAnimController>()
+            .extract_entity(entity)
+            .build();
+        let serialized = scene.serialize(&registry.read()).unwrap();
+
+        let mut deserializer = ron::de::Deserializer::from_str(&serialized).unwrap();
+        let scene = SceneDeserializer {
+            type_registry: &registry.read(),
+        }
+        .deserialize(&mut deserializer)
+        .unwrap();
+        let mut restored = TestApp::new();
+        let mut entity_map = EntityHashMap::default();
+        scene
+            .write_to_world(restored.app.world_mut(), &mut entity_map)
+            .unwrap();
+        let restored_entity = entity_map[&entity];
+        (restored, restored_entity, serialized)
+    }
+
+    #[test]
+    fn controllers_round_trip_through_scenes() {
+        let mut app = TestApp::new();
+        let controller =
+            controller(SpriteAnimMode::Mirror).with_anim(SpriteAnimData::new("Other", vec![1, 2]));
+        let entity = app.spawn(controller);
+        assert_eq!(app.frames(entity, 5), vec![10, 11, 12, 13, 12]);
+
+        let (mut restored, restored_entity, _) = round_trip(&app, entity);
+        restored
+            .app
+            .world_mut()
+            .entity_mut(restored_entity)
+            .insert(TextureAtlas::default());
+
+        // The restored controller resumes where the original one was
+        assert_eq!(
+            restored
+                .controller_mut(restored_entity)
+                .frame_index(),
+            2
+        );
+        assert_eq!(restored.frames(restored_entity, 3), app.frames(entity, 3));
+        let mut ac = restored.controller_mut(restored_entity);
+        assert_eq!(ac.current().name(), "Anim");
+        ac.play("Other").unwrap();
+    }
+
+    #[test]
+    fn library_asset_controllers_round_trip_through_scenes() {
+        let library = SpriteAnimLibrary::new()
+            .with_anim(SpriteAnimData::new("Idle", FRAMES.to_vec()))
+            .with_anim(SpriteAnimData::new("Run", vec![20, 21, 22, 23]))
+            .with_default("Idle");
+        let mut app = TestApp::new();
+        let handle = app
+            .app
+            .world_mut()
+            .resource_mut::<Assets<SpriteAnimLibrary>>()
+            .add(library.clone());
+        let entity = app
+            .app
+            .world_mut()
+            .spawn((handle, TextureAtlas::default()))
+            .id();
+        app.step();
+        app.controller_mut(entity).play("Run").unwrap();
+        assert_eq!(app.frames(entity, 2), vec![20, 21]);
+
+        // Only the name of the current animation is saved, the library stays in the asset
+        let (mut restored, restored_entity, serialized) = round_trip(&app, entity);
+        assert!(!serialized.contains("frames"));
+        assert!(restored
+            .controller_mut(restored_entity)
+            .is_restoring());
+
+        // The restored controller waits for its library asset, and resumes once it is loaded
+        restored.step();
+        let handle = restored
+            .app
+            .world_mut()
+            .resource_mut::<Assets<SpriteAnimLibrary>>()
+            .add(library);
+        restored
+            .app
+            .world_mut()
+            .entity_mut(restored_entity)
+            .insert((handle.clone(), TextureAtlas::default()));
+        assert_eq!(restored.frames(restored_entity, 3), app.frames(entity, 3));
+
+        let asset = restored
+            .app
+            .world()
+            .resource::<Assets<SpriteAnimLibrary>>()
+            .get(&handle)
+            .unwrap()
+            .clone();
+        let ac = restored.controller_mut(restored_entity);
+        assert!(!ac.is_restoring());
+        assert!(ac.library().ptr_eq(&asset));
+        assert_eq!(ac.current().name(), "Run");
     }
 }
@@ -0,0 +1,430 @@
+use std::{
+    ops::{Deref, DerefMut},
+    sync::Arc,
+};
+
+use bevy::{prelude::*, utils::HashMap};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{SpriteAnimController, SpriteAnimData, SpriteAnimError, SpriteAnimKey};
+
+/// Collection of animations shared by every `SpriteAnimController` created from it.
+///
+/// Cloning a library only clones a reference to its animations, and controllers only store the
+/// state of their own playback. Spawning many entities playing the same animations, or switching
+/// between animations, never copies the animations themselves.
+///
+/// Libraries can also be added to `Assets<SpriteAnimLibrary>`: entities holding a
+/// `Handle<SpriteAnimLibrary>` get a `SpriteAnimController` using it, and pick up the changes
+/// made to the asset. The asset then takes priority over animations added to the controller.
+///
+/// Example usage:
+/// ```Rust
+/// // Creation
+/// let library = SpriteAnimLibrary::new()
+///     .with_anim(SpriteAnimData::new("Idle", vec![0, 1, 2, 3]))
+///     .with_anim(SpriteAnimData::new("Run", vec![8, 9, 10, 11]))
+///     .with_default("Idle");
+///
+/// // Sharing through an asset
+/// let library = libraries.add(library);
+/// for _ in 0..200 {
+///     commands.spawn((
+///         SpriteBundle {
+///             texture: texture.clone(),
+///             ..default()
+///         },
+///         TextureAtlas::from(layout.clone()),
+///         library.clone(),
+///     ));
+/// }
+///
+/// // Sharing without an asset
+/// let controller = SpriteAnimController::from_library(&library).with_fps(8.0);
+/// ```
+///
+/// Libraries are reflected as opaque values. Controllers using a library asset are saved
+/// without it, and get it back once their entity holds the `Handle<SpriteAnimLibrary>` again.
+/// Other controllers are saved with a copy of their animations.
+#[derive(Asset, Reflect, Debug, Clone, Serialize, Deserialize)]
+#[reflect_value(Debug, Default, Serialize, Deserialize)]
+pub struct SpriteAnimLibrary {
+    animations: Arc<HashMap<String, Arc<SpriteAnimData>>>,
+    duplicates: Arc<Vec<String>>,
+}
+
+impl Default for SpriteAnimLibrary {
+    /// Creates a library holding only a "Default" animation, which always displays frame 0
+    fn default() -> Self {
+        let mut animations = HashMap::new();
+        animations.insert("Default".to_string(), Arc::new(SpriteAnimData::default()));
+        Self {
+            animations: Arc::new(animations),
+            duplicates: Arc::new(Vec::new()),
+        }
+    }
+}
+
+impl FromIterator<SpriteAnimData> for SpriteAnimLibrary {
+    fn from_iter<T: IntoIterator<Item = SpriteAnimData>>(iter: T) -> Self {
+        iter.into_iter()
+            .fold(Self::default(), |library, anim_data| {
+                library.with_anim(anim_data)
+            })
+    }
+}
+
+impl SpriteAnimLibrary {
+    /// Creates a library holding only a "Default" animation
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the animation played when trying to play an animation that does not exist in the
+    /// library.
+    pub fn with_default_anim(mut self, anim_data: SpriteAnimData) -> Self {
+        self.set_default_anim(anim_data);
+        self
+    }
+
+    /// Uses the animation `anim` of the library as the default animation, if it exists
+    pub fn with_default(mut self, anim: impl SpriteAnimKey) -> Self {
+        if let Some(anim) = self.shared(anim.anim_name()) {
+            Arc::make_mut(&mut self.animations).insert("Default".to_string(), anim);
+        }
+        self
+    }
+
+    /// Adds an animation to the library on instantiation.
+    /// An animation with the same name is replaced, which is reported as a
+    /// `SpriteAnimError::DuplicateName` once the library is validated.
+    pub fn with_anim(mut self, anim_data: SpriteAnimData) -> Self {
+        self.replace_anim(anim_data);
+        self
+    }
+
+    /// Adds an animation to the library.
+    /// Returns an error, without adding it, if it has no frames or if its name is already taken.
+    pub fn insert_anim(&mut self, anim_data: SpriteAnimData) -> Result<(), SpriteAnimError> {
+        if anim_data.frames.is_empty() {
+            return Err(SpriteAnimError::NoFrames(anim_data.name));
+        }
+        if self.animations.contains_key(&anim_data.name) {
+            return Err(SpriteAnimError::DuplicateName(anim_data.name));
+        }
+        Arc::make_mut(&mut self.animations).insert(anim_data.name.clone(), Arc::new(anim_data));
+        Ok(())
+    }
+
+    /// Returns the animation `anim`, if it is part of the library
+    pub fn get(&self, anim: impl SpriteAnimKey) -> Option<&SpriteAnimData> {
+        self.animations
+            .get(anim.anim_name())
+            .map(AsRef::as_ref)
+    }
+
+    /// Returns true if the animation is part of the library
+    pub fn has_animation(&self, anim: impl SpriteAnimKey) -> bool {
+        self.animations.contains_key(anim.anim_name())
+    }
+
+    /// Returns the names of every animation of the library, including "Default"
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.animations.keys().map(String::as_str)
+    }
+
+    /// Checks every animation of the library against `layout`, if given, and returns every
+    /// problem found: empty animations, frames out of range and duplicate names.
+    pub fn validate(&self, layout: Option<&TextureAtlasLayout>) -> Vec<SpriteAnimError> {
+        let duplicates = self
+            .duplicates
+            .iter()
+            .map(|name| SpriteAnimError::DuplicateName(name.clone()));
+        let animations = self
+            .animations
+            .values()
+            .flat_map(|anim| anim.validate(layout));
+        duplicates.chain(animations).collect()
+    }
+
+    /// Returns true if both libraries share the same animations
+    pub fn ptr_eq(&self, other: &SpriteAnimLibrary) -> bool {
+        Arc::ptr_eq(&self.animations, &other.animations)
+    }
+
+    pub(crate) fn set_default_anim(&mut self, anim_data: SpriteAnimData) {
+        Arc::make_mut(&mut self.animations).insert("Default".to_string(), Arc::new(anim_data));
+    }
+
+    /// Adds an animation, replacing and reporting any other animation with the same name.
+    /// The default animation can be replaced freely.
+    pub(crate) fn replace_anim(&mut self, anim_data: SpriteAnimData) {
+        let name = anim_data.name.clone();
+        let previous =
+            Arc::make_mut(&mut self.animations).insert(name.clone(), Arc::new(anim_data));
+        if previous.is_some() && name != "Default" {
+            Arc::make_mut(&mut self.duplicates).push(name);
+        }
+    }
+
+    /// Returns a shared reference to the animation named `anim_name`
+    pub(crate) fn shared(&self, anim_name: &str) -> Option<Arc<SpriteAnimData>> {
+        self.animations.get(anim_name).cloned()
+    }
+
+    /// Returns a shared reference to the default animation
+    pub(crate) fn default_anim(&self) -> Arc<SpriteAnimData> {
+        self.shared("Default")
+            .expect("Default should exist")
+    }
+}
+
+/// Library of a `SpriteAnimController`, which remembers whether it comes from an asset.
+///
+/// Saved as a copy of the library, or as nothing if it comes from an asset: the controller then
+/// waits for its entity to hold the asset again, see `SpriteAnimController::is_restoring`.
+#[derive(Reflect, Debug, Clone, Default)]
+#[reflect_value(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct ControllerLibrary {
+    library: SpriteAnimLibrary,
+    /// Given by a `Handle<SpriteAnimLibrary>` or `Handle<SpriteAnimSheet>` of the entity
+    pub from_asset: bool,
+    /// Restored from a save, and not yet given its library back
+    pub restoring: bool,
+}
+
+impl ControllerLibrary {
+    pub fn new(library: SpriteAnimLibrary, from_asset: bool) -> Self {
+        Self {
+            library,
+            from_asset,
+            restoring: false,
+        }
+    }
+}
+
+impl Deref for ControllerLibrary {
+    type Target = SpriteAnimLibrary;
+
+    fn deref(&self) -> &SpriteAnimLibrary {
+        &self.library
+    }
+}
+
+impl DerefMut for ControllerLibrary {
+    fn deref_mut(&mut self) -> &mut SpriteAnimLibrary {
+        &mut self.library
+    }
+}
+
+impl Serialize for ControllerLibrary {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (!self.from_asset)
+            .then_some(&self.library)
+            .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ControllerLibrary {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let library = Option::<SpriteAnimLibrary>::deserialize(deserializer)?;
+        Ok(Self {
+            from_asset: library.is_none(),
+            library: library.unwrap_or_default(),
+            restoring: true,
+        })
+    }
+}
+
+/// System that gives entities holding a `Handle<SpriteAnimLibrary>` a `SpriteAnimController`
+/// using it once loaded, and updates their controller whenever the asset changes.
+///
+/// On change, the controller keeps playing the same animation, from where it was, if it still
+/// exists.
+pub(crate) fn apply_sprite_anim_libraries(
+    mut commands: Commands,
+    libraries: Res<Assets<SpriteAnimLibrary>>,
+    mut query: Query<(
+        Entity,
+        &Handle<SpriteAnimLibrary>,
+        Option<&mut SpriteAnimController>,
+    )>,
+) {
+    for (entity, handle, controller) in query.iter_mut() {
+        let Some(library) = libraries.get(handle) else {
+            continue;
+        };
+        match controller {
+            None => {
+                commands
+                    .entity(entity)
+                    .insert(SpriteAnimController::from_library(library).shared_by_asset());
+            }
+            // Modifying a shared library gives it new animations, which are not shared with the
+            // controllers anymore. Restored controllers get their library back.
+            Some(mut controller) if !controller.library().ptr_eq(library) => {
+                controller.set_library_asset(library.clone());
+            }
+            Some(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::test_utils::TestApp;
+
+    fn library() -> SpriteAnimLibrary {
+        SpriteAnimLibrary::new()
+            .with_anim(SpriteAnimData::new("Idle", vec![0, 1, 2, 3]))
+            .with_anim(SpriteAnimData::new("Run", vec![4, 5, 6, 7, 8, 9]))
+            .with_default("Idle")
+    }
+
+    /// Spawns `count` entities using a library asset, and returns them once they have a
+    /// `SpriteAnimController`
+    fn spawn_from_asset(app: &mut TestApp, count: usize) -> Vec<Entity> {
+        let handle = app
+            .app
+            .world_mut()
+            .resource_mut::<Assets<SpriteAnimLibrary>>()
+            .add(library());
+        let entities = (0..count)
+            .map(|_| {
+                app.app
+                    .world_mut()
+                    .spawn((handle.clone(), TextureAtlas::default()))
+                    .id()
+            })
+            .collect();
+        app.step();
+        entities
+    }
+
+    #[test]
+    fn controllers_share_the_library_asset() {
+        let mut app = TestApp::new();
+        let entities = spawn_from_asset(&mut app, 3);
+        for entity in &entities {
+            app.controller_mut(*entity).play("Run").unwrap();
+        }
+        let first = app.controller_mut(entities[0]).library().clone();
+        for entity in &entities {
+            let ac = app.controller_mut(*entity);
+            assert!(ac.library().ptr_eq(&first));
+            assert_eq!(ac.current().name(), "Run");
+        }
+
+        // Changes to the asset reach every controller, which keep playing their animation
+        let mut query = app
+            .app
+            .world_mut()
+            .query::<&Handle<SpriteAnimLibrary>>();
+        let handle = query
+            .iter(app.app.world())
+            .next()
+            .unwrap()
+            .clone();
+        app.app
+            .world_mut()
+            .resource_mut::<Assets<SpriteAnimLibrary>>()
+            .get_mut(&handle)
+            .unwrap()
+            .insert_anim(SpriteAnimData::new("Jump", vec![10, 11]))
+            .unwrap();
+        app.step();
+        for entity in &entities {
+            let ac = app.controller_mut(*entity);
+            assert!(!ac.library().ptr_eq(&first));
+            assert!(ac.has_animation("Jump"));
+            assert_eq!(ac.current().name(), "Run");
+        }
+    }
+
+    #[test]
+    fn default_anim_is_not_a_duplicate() {
+        let library = SpriteAnimLibrary::new()
+            .with_anim(SpriteAnimData::new("Default", vec![0, 1]))
+            .with_anim(SpriteAnimData::new("Idle", vec![0]))
+            .with_anim(SpriteAnimData::new("Idle", vec![1]));
+        let errors = library.validate(None);
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(&errors[0], SpriteAnimError::DuplicateName(name) if name == "Idle"));
+    }
+
+    /// Runs `updates` updates during which every entity switches animation, and returns how long
+    /// they took
+    fn switch_every_update(app: &mut TestApp, entities: &[Entity], updates: usize) -> Duration {
+        let start = Instant::now();
+        for update in 0..updates {
+            let anim_name = if update % 2 == 0 { "Run" } else { "Idle" };
+            for entity in entities {
+                app.controller_mut(*entity).play(anim_name).ok();
+            }
+            app.step();
+        }
+        start.elapsed()
+    }
+
+    /// Compares controllers sharing a library asset with controllers holding their own copy of
+    /// the animations, and checks that switching animations keeps the asset shared.
+    /// Run with `cargo test --release -- --ignored --nocapture`.
+    #[test]
+    #[ignore = "stress test"]
+    fn stress_shared_library() {
+        const ENTITIES: usize = 10_000;
+        const UPDATES: usize = 100;
+
+        let mut shared = TestApp::new();
+        let start = Instant::now();
+        let entities = spawn_from_asset(&mut shared, ENTITIES);
+        let shared_spawn = start.elapsed();
+        let shared_switch = switch_every_update(&mut shared, &entities, UPDATES);
+        let handle = shared
+            .app
+            .world()
+            .get::<Handle<SpriteAnimLibrary>>(entities[0])
+            .unwrap()
+            .clone();
+        let library = shared
+            .app
+            .world()
+            .resource::<Assets<SpriteAnimLibrary>>()
+            .get(&handle)
+            .unwrap()
+            .clone();
+        for entity in &entities {
+            assert!(shared
+                .controller_mut(*entity)
+                .library()
+                .ptr_eq(&library));
+        }
+
+        let mut owned = TestApp::new();
+        let start = Instant::now();
+        let entities: Vec<Entity> = (0..ENTITIES)
+            .map(|_| {
+                let controller = SpriteAnimController::default()
+                    .with_anim(SpriteAnimData::new("Idle", vec![0, 1, 2, 3]))
+                    .with_anim(SpriteAnimData::new("Run", vec![4, 5, 6, 7, 8, 9]));
+                owned.spawn(controller)
+            })
+            .collect();
+        owned.step();
+        let owned_spawn = start.elapsed();
+        let owned_switch = switch_every_update(&mut owned, &entities, UPDATES);
+
+        println!("{} entities, {} updates", ENTITIES, UPDATES);
+        println!(
+            "shared: spawn {:?}, switching {:?}",
+            shared_spawn, shared_switch
+        );
+        println!(
+            "owned:  spawn {:?}, switching {:?}",
+            owned_spawn, owned_switch
+        );
+    }
+}
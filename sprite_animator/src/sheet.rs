@@ -0,0 +1,471 @@
+use core::fmt;
+use std::time::Duration;
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext, ParseAssetPathError},
+    prelude::*,
+    utils::HashMap,
+};
+use serde::Deserialize;
+
+use crate::{SpriteAnimController, SpriteAnimData, SpriteAnimLibrary, SpriteAnimMode};
+
+/// Sprite sheet and the animations it contains, loaded from a `.anim.ron` or `.anim.json` file.
+/// Every entity animated from the same sheet shares its `SpriteAnimLibrary`.
+///
+/// The image path is relative to the animation file. Frame durations are in milliseconds and
+/// markers are keyed by the position of the frame in the `frames` vector.
+///
+/// Example file (`char_blue.anim.ron`):
+/// ```ron
+/// (
+///     image: "char_blue.png",
+///     tile_size: (56, 56),
+///     columns: 8,
+///     rows: 7,
+///     fps: 8.0,
+///     default: Some("Idle"),
+///     animations: [
+///         (name: "Idle", frames: [0, 1, 2, 3, 4, 5]),
+///         (
+///             name: "Attack",
+///             frames: [8, 9, 10, 11, 12, 13, 14, 15],
+///             mode: Once,
+///             fps: Some(12.0),
+///             frame_durations_ms: {0: 200},
+///             markers: {4: ["hit"]},
+///         ),
+///     ],
+/// )
+/// ```
+#[derive(Asset, TypePath, Debug)]
+pub struct SpriteAnimSheet {
+    #[dependency]
+    pub image: Handle<Image>,
+    #[dependency]
+    pub layout: Handle<TextureAtlasLayout>,
+    pub fps: f32,
+    pub default: Option<String>,
+    pub library: SpriteAnimLibrary,
+}
+
+impl SpriteAnimSheet {
+    /// Creates a new sheet holding `animations`, using the animation named `default`, if any, as
+    /// the default animation of its library.
+    pub fn new(
+        image: Handle<Image>,
+        layout: Handle<TextureAtlasLayout>,
+        fps: f32,
+        default: Option<String>,
+        animations: Vec<SpriteAnimData>,
+    ) -> Self {
+        let mut library: SpriteAnimLibrary = animations.into_iter().collect();
+        if let Some(default) = &default {
+            library = library.with_default(default);
+        }
+        Self {
+            image,
+            layout,
+            fps,
+            default,
+            library,
+        }
+    }
+
+    /// Creates a `SpriteAnimController` sharing the animations of the sheet.
+    /// The default animation of the sheet, if any, is also the one currently playing.
+    pub fn controller(&self) -> SpriteAnimController {
+        let mut controller = SpriteAnimController::from_library(&self.library).with_fps(self.fps);
+        if let Some(default) = &self.default {
+            controller.restart(default).ok();
+        }
+        controller
+    }
+}
+
+/// Bundle of components required to display a sprite animated from a `SpriteAnimSheet`.
+/// The `SpriteAnimController`, `TextureAtlas` and texture are added once the sheet is loaded.
+///
+/// Example usage:
+/// ```Rust
+/// commands.spawn(SpriteAnimSheetBundle {
+///     sheet: asset_server.load("oak_woods/character/char_blue.anim.ron"),
+///     ..default()
+/// });
+/// ```
+#[derive(Bundle, Default)]
+pub struct SpriteAnimSheetBundle {
+    pub sprite_bundle: SpriteBundle,
+    pub sheet: Handle<SpriteAnimSheet>,
+}
+
+/// File representation of a `SpriteAnimSheet`
+#[derive(Deserialize)]
+struct SpriteAnimSheetDef {
+    image: String,
+    tile_size: (u32, u32),
+    columns: u32,
+    rows: u32,
+    #[serde(default)]
+    padding: Option<(u32, u32)>,
+    #[serde(default)]
+    offset: Option<(u32, u32)>,
+    #[serde(default = "default_fps")]
+    fps: f32,
+    #[serde(default)]
+    default: Option<String>,
+    animations: Vec<SpriteAnimDef>,
+}
+
+/// File representation of a `SpriteAnimData`
+#[derive(Deserialize)]
+struct SpriteAnimDef {
+    name: String,
+    frames: Vec<usize>,
+    #[serde(default)]
+    mode: SpriteAnimMode,
+    #[serde(default)]
+    fps: Option<f32>,
+    #[serde(default)]
+    frame_durations_ms: HashMap<usize, u64>,
+    #[serde(default)]
+    markers: HashMap<usize, Vec<String>>,
+}
+
+impl SpriteAnimSheetDef {
+    /// Parses a RON, or JSON, animation file and checks that its default animation exists
+    fn parse(bytes: &[u8], is_json: bool) -> Result<Self, SpriteAnimSheetLoaderError> {
+        let def: Self = if is_json {
+            serde_json::from_slice(bytes)?
+        } else {
+            ron::de::from_bytes(bytes)?
+        };
+
+        if let Some(default) = &def.default {
+            if !def
+                .animations
+                .iter()
+                .any(|anim| &anim.name == default)
+            {
+                return Err(SpriteAnimSheetLoaderError::UnknownDefault(default.clone()));
+            }
+        }
+        Ok(def)
+    }
+}
+
+fn default_fps() -> f32 {
+    4.0
+}
+
+impl From<SpriteAnimDef> for SpriteAnimData {
+    fn from(def: SpriteAnimDef) -> Self {
+        let mut anim = SpriteAnimData::new(def.name, def.frames).with_mode(def.mode);
+        if let Some(fps) = def.fps {
+            anim = anim.with_fps(fps);
+        }
+        for (index, millis) in def.frame_durations_ms {
+            anim = anim.with_frame_duration(index, Duration::from_millis(millis));
+        }
+        for (index, markers) in def.markers {
+            for marker in markers {
+                anim = anim.with_marker(index, marker);
+            }
+        }
+        anim
+    }
+}
+
+#[derive(Debug)]
+pub enum SpriteAnimSheetLoaderError {
+    Io(std::io::Error),
+    Ron(ron::error::SpannedError),
+    Json(serde_json::Error),
+    Path(ParseAssetPathError),
+    UnknownDefault(String),
+}
+
+impl fmt::Display for SpriteAnimSheetLoaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "Could not read the animation file: {}", err),
+            Self::Ron(err) => write!(f, "Could not parse the RON animation file: {}", err),
+            Self::Json(err) => write!(f, "Could not parse the JSON animation file: {}", err),
+            Self::Path(err) => write!(f, "Invalid image path: {}", err),
+            Self::UnknownDefault(msg) => write!(
+                f,
+                "The default animation \"{}\" is not part of the animation file",
+                msg
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SpriteAnimSheetLoaderError {}
+
+impl From<std::io::Error> for SpriteAnimSheetLoaderError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<ron::error::SpannedError> for SpriteAnimSheetLoaderError {
+    fn from(err: ron::error::SpannedError) -> Self {
+        Self::Ron(err)
+    }
+}
+
+impl From<serde_json::Error> for SpriteAnimSheetLoaderError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Json(err)
+    }
+}
+
+impl From<ParseAssetPathError> for SpriteAnimSheetLoaderError {
+    fn from(err: ParseAssetPathError) -> Self {
+        Self::Path(err)
+    }
+}
+
+/// Loads `SpriteAnimSheet` assets from `.anim.ron` and `.anim.json` files
+#[derive(Default)]
+pub struct SpriteAnimSheetLoader;
+
+impl AssetLoader for SpriteAnimSheetLoader {
+    type Asset = SpriteAnimSheet;
+    type Settings = ();
+    type Error = SpriteAnimSheetLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+
+        let is_json = load_context
+            .path()
+            .extension()
+            .is_some_and(|ext| ext == "json");
+        let def = SpriteAnimSheetDef::parse(&bytes, is_json)?;
+
+        let image_path = load_context
+            .asset_path()
+            .resolve_embed(&def.image)?;
+        let image = load_context.load(image_path);
+
+        let layout = TextureAtlasLayout::from_grid(
+            UVec2::from(def.tile_size),
+            def.columns,
+            def.rows,
+            def.padding.map(UVec2::from),
+            def.offset.map(UVec2::from),
+        );
+        let layout = load_context.add_labeled_asset("layout".to_string(), layout);
+
+        Ok(SpriteAnimSheet::new(
+            image,
+            layout,
+            def.fps,
+            def.default,
+            def.animations
+                .into_iter()
+                .map(SpriteAnimData::from)
+                .collect(),
+        ))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["anim.ron", "anim.json"]
+    }
+}
+
+/// System that sets up entities holding a `Handle<SpriteAnimSheet>` once their sheet is loaded,
+/// and updates their animations when the file is hot-reloaded.
+///
+/// On reload, the controller keeps playing the same animation, from where it was, if it still
+/// exists. Controllers restored from a save get the animations of the sheet back.
+#[allow(clippy::type_complexity)]
+pub(crate) fn apply_sprite_anim_sheets(
+    mut commands: Commands,
+    mut asset_events: EventReader<AssetEvent<SpriteAnimSheet>>,
+    sheets: Res<Assets<SpriteAnimSheet>>,
+    mut query: Query<(
+        Entity,
+        &Handle<SpriteAnimSheet>,
+        Option<&mut SpriteAnimController>,
+        Option<&mut TextureAtlas>,
+    )>,
+) {
+    let modified: Vec<AssetId<SpriteAnimSheet>> = asset_events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect();
+
+    for (entity, handle, controller, texture_atlas) in query.iter_mut() {
+        let Some(sheet) = sheets.get(handle) else {
+            continue;
+        };
+        match controller {
+            None => {
+                commands.entity(entity).insert((
+                    sheet.controller().shared_by_asset(),
+                    TextureAtlas::from(sheet.layout.clone()),
+                    sheet.image.clone(),
+                ));
+            }
+            Some(mut controller)
+                if modified.contains(&handle.id()) || controller.is_restoring() =>
+            {
+                if controller.is_restoring() {
+                    controller.set_library_asset(sheet.library.clone());
+                } else {
+                    controller.reload(sheet.controller());
+                }
+                if let Some(mut texture_atlas) = texture_atlas {
+                    texture_atlas.layout = sheet.layout.clone();
+                }
+                commands
+                    .entity(entity)
+                    .insert(sheet.image.clone());
+            }
+            Some(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TestApp;
+
+    const RON: &str = r#"(
+        image: "char_blue.png",
+        tile_size: (56, 56),
+        columns: 8,
+        rows: 7,
+        default: Some("Idle"),
+        animations: [
+            (name: "Idle", frames: [0, 1, 2, 3, 4, 5]),
+            (
+                name: "Attack",
+                frames: [8, 9, 10, 11],
+                mode: Once,
+                fps: Some(12.0),
+                frame_durations_ms: {0: 200},
+                markers: {2: ["hit"]},
+            ),
+        ],
+    )"#;
+
+    const JSON: &str = r#"{
+        "image": "char_blue.png",
+        "tile_size": [56, 56],
+        "columns": 8,
+        "rows": 7,
+        "fps": 8.0,
+        "animations": [
+            { "name": "Run", "frames": [16, 17, 18, 19], "mode": { "Loop": 2 } }
+        ]
+    }"#;
+
+    fn sheet(def: SpriteAnimSheetDef) -> SpriteAnimSheet {
+        SpriteAnimSheet::new(
+            Handle::default(),
+            Handle::default(),
+            def.fps,
+            def.default,
+            def.animations
+                .into_iter()
+                .map(SpriteAnimData::from)
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn parse_ron() {
+        let def = SpriteAnimSheetDef::parse(RON.as_bytes(), false).unwrap();
+        assert_eq!(def.image, "char_blue.png");
+        assert_eq!((def.tile_size, def.columns, def.rows), ((56, 56), 8, 7));
+        assert_eq!(def.fps, default_fps());
+
+        let controller = sheet(def).controller();
+        assert_eq!(controller.current().name(), "Idle");
+        let attack = controller.library().get("Attack").unwrap();
+        assert_eq!(attack.mode(), &SpriteAnimMode::Once);
+        assert_eq!(attack.frame_duration(0), Some(Duration::from_millis(200)));
+        assert_eq!(
+            attack.frame_duration(1),
+            Some(Duration::from_secs_f32(1.0 / 12.0))
+        );
+        assert_eq!(attack.markers(2), ["hit".to_string()]);
+    }
+
+    #[test]
+    fn parse_json() {
+        let def = SpriteAnimSheetDef::parse(JSON.as_bytes(), true).unwrap();
+        assert_eq!(def.fps, 8.0);
+        assert_eq!(def.default, None);
+
+        let controller = sheet(def).controller();
+        assert_eq!(controller.fps(), 8.0);
+        let run = controller.library().get("Run").unwrap();
+        assert_eq!(run.frames(), [16, 17, 18, 19]);
+        assert_eq!(run.mode(), &SpriteAnimMode::Loop(2));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(matches!(
+            SpriteAnimSheetDef::parse(JSON.as_bytes(), false),
+            Err(SpriteAnimSheetLoaderError::Ron(_))
+        ));
+        assert!(matches!(
+            SpriteAnimSheetDef::parse(RON.as_bytes(), true),
+            Err(SpriteAnimSheetLoaderError::Json(_))
+        ));
+        let unknown_default = RON.replace(r#"Some("Idle")"#, r#"Some("Jump")"#);
+        assert!(matches!(
+            SpriteAnimSheetDef::parse(unknown_default.as_bytes(), false),
+            Err(SpriteAnimSheetLoaderError::UnknownDefault(name)) if name == "Jump"
+        ));
+    }
+
+    #[test]
+    fn hot_reload_keeps_the_current_animation() {
+        let mut app = TestApp::new();
+        let def = SpriteAnimSheetDef::parse(RON.as_bytes(), false).unwrap();
+        let handle = app
+            .app
+            .world_mut()
+            .resource_mut::<Assets<SpriteAnimSheet>>()
+            .add(sheet(def));
+        let entity = app
+            .app
+            .world_mut()
+            .spawn((handle.clone(), TextureAtlas::default()))
+            .id();
+        app.step();
+        app.controller_mut(entity).play("Attack").unwrap();
+        app.step();
+
+        let reloaded = RON.replace("[8, 9, 10, 11]", "[40, 41]");
+        let def = SpriteAnimSheetDef::parse(reloaded.as_bytes(), false).unwrap();
+        app.app
+            .world_mut()
+            .resource_mut::<Assets<SpriteAnimSheet>>()
+            .insert(&handle, sheet(def));
+        // Asset events are sent at the end of the update following the change
+        app.step();
+        app.step();
+
+        let controller = app.controller_mut(entity);
+        assert_eq!(controller.current().name(), "Attack");
+        assert_eq!(controller.current().frames(), [40, 41]);
+    }
+}
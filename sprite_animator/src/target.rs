@@ -0,0 +1,208 @@
+use bevy::prelude::*;
+
+use crate::{SpriteAnimConfig, SpriteAnimController, SpriteAnimSet};
+
+/// Component driven by the frames of the `SpriteAnimController` of its entity.
+///
+/// `TextureAtlas` is a target by default, for both sprites and UI images. Other targets must be
+/// registered with `add_sprite_anim_target`.
+///
+/// Example usage:
+/// ```Rust
+/// #[derive(Component)]
+/// struct Flicker(Color);
+///
+/// impl SpriteAnimTarget for Flicker {
+///     fn set_frame(&mut self, frame: usize) {
+///         self.0.set_alpha(if frame % 2 == 0 { 1.0 } else { 0.5 });
+///     }
+/// }
+///
+/// app.add_sprite_anim_target::<Flicker>();
+/// ```
+pub trait SpriteAnimTarget: Component {
+    /// Displays `frame`, taken from the `frames` vector of the current animation
+    fn set_frame(&mut self, frame: usize);
+}
+
+impl SpriteAnimTarget for TextureAtlas {
+    fn set_frame(&mut self, frame: usize) {
+        self.index = frame;
+    }
+}
+
+/// Images displayed by an animated sprite or UI image, for sheets made of separate files.
+/// The frames of the animations are indices in `images`, and replace the `Handle<Image>` of
+/// sprites or the `UiImage` of UI nodes.
+///
+/// Example usage:
+/// ```Rust
+/// let images = SpriteAnimImages::new(vec![
+///     asset_server.load("oak_woods/decorations/grass_1.png"),
+///     asset_server.load("oak_woods/decorations/grass_2.png"),
+///     asset_server.load("oak_woods/decorations/grass_3.png"),
+/// ]);
+/// let mut controller =
+///     SpriteAnimController::default().with_anim(SpriteAnimData::new("Sway", vec![0, 1, 2, 1]));
+/// controller.play("Sway").ok();
+/// commands.spawn((SpriteBundle::default(), controller, images));
+/// ```
+#[derive(Component, Debug, Clone, Default)]
+pub struct SpriteAnimImages {
+    images: Vec<Handle<Image>>,
+}
+
+impl SpriteAnimImages {
+    /// Creates a new `SpriteAnimImages` displaying the image at the index of each frame
+    pub fn new(images: Vec<Handle<Image>>) -> Self {
+        Self { images }
+    }
+
+    /// Returns the image displayed for `frame`, if any
+    pub fn get(&self, frame: usize) -> Option<&Handle<Image>> {
+        self.images.get(frame)
+    }
+}
+
+/// Registers additional `SpriteAnimTarget`s on the `App`
+pub trait SpriteAnimAppExt {
+    /// Makes every `SpriteAnimController` drive the component `T` of its entity.
+    /// Must be called after adding the `SpriteAnimationPlugin`.
+    fn add_sprite_anim_target<T: SpriteAnimTarget>(&mut self) -> &mut Self;
+}
+
+impl SpriteAnimAppExt for App {
+    fn add_sprite_anim_target<T: SpriteAnimTarget>(&mut self) -> &mut Self {
+        let schedule = self
+            .world()
+            .get_resource::<SpriteAnimConfig>()
+            .expect("SpriteAnimationPlugin should be added before its targets")
+            .schedule;
+        self.add_systems(
+            schedule,
+            apply_sprite_anim_target::<T>.in_set(SpriteAnimSet::Apply),
+        )
+    }
+}
+
+/// System that sets the frame of every `SpriteAnimTarget` of type `T` whose controller just
+/// displayed a new frame.
+pub(crate) fn apply_sprite_anim_target<T: SpriteAnimTarget>(
+    mut query: Query<(&SpriteAnimController, &mut T)>,
+) {
+    for (ac, mut target) in query.iter_mut() {
+        if ac.frame_changed {
+            target.set_frame(ac.frame());
+        }
+    }
+}
+
+/// System that displays the image of the current frame of every `SpriteAnimImages`
+#[allow(clippy::type_complexity)]
+pub(crate) fn apply_sprite_anim_images(
+    mut query: Query<(
+        &SpriteAnimController,
+        &SpriteAnimImages,
+        Option<&mut Handle<Image>>,
+        Option<&mut UiImage>,
+    )>,
+) {
+    for (ac, images, texture, ui_image) in query.iter_mut() {
+        if !ac.frame_changed {
+            continue;
+        }
+        let Some(image) = images.get(ac.frame()) else {
+            continue;
+        };
+        if let Some(mut texture) = texture {
+            if *texture != *image {
+                *texture = image.clone();
+            }
+        }
+        if let Some(mut ui_image) = ui_image {
+            if ui_image.texture != *image {
+                ui_image.texture = image.clone();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_utils::TestApp, SpriteAnimData};
+
+    #[derive(Component, Default)]
+    struct Frames(Vec<usize>);
+
+    impl SpriteAnimTarget for Frames {
+        fn set_frame(&mut self, frame: usize) {
+            self.0.push(frame);
+        }
+    }
+
+    fn controller() -> SpriteAnimController {
+        let mut controller =
+            SpriteAnimController::default().with_anim(SpriteAnimData::new("Anim", vec![2, 0, 1]));
+        controller.play("Anim").unwrap();
+        controller
+    }
+
+    #[test]
+    fn custom_targets() {
+        let mut app = TestApp::new();
+        app.app.add_sprite_anim_target::<Frames>();
+        let entity = app
+            .app
+            .world_mut()
+            .spawn((controller(), Frames::default()))
+            .id();
+        for _ in 0..4 {
+            app.step();
+        }
+        let frames = app.app.world().get::<Frames>(entity).unwrap();
+        assert_eq!(frames.0, vec![2, 0, 1, 2]);
+    }
+
+    #[test]
+    fn images_of_sprites_and_ui_nodes() {
+        let mut app = TestApp::new();
+        let images: Vec<Handle<Image>> = {
+            let mut assets = app
+                .app
+                .world_mut()
+                .resource_mut::<Assets<Image>>();
+            (0..3)
+                .map(|_| assets.add(Image::default()))
+                .collect()
+        };
+        let sprite = app
+            .app
+            .world_mut()
+            .spawn((
+                controller(),
+                SpriteAnimImages::new(images.clone()),
+                Handle::<Image>::default(),
+            ))
+            .id();
+        let ui_node = app
+            .app
+            .world_mut()
+            .spawn((
+                controller(),
+                SpriteAnimImages::new(images.clone()),
+                UiImage::default(),
+            ))
+            .id();
+
+        for expected in [2, 0, 1] {
+            app.step();
+            let world = app.app.world();
+            assert_eq!(world.get::<Handle<Image>>(sprite), Some(&images[expected]));
+            assert_eq!(
+                world.get::<UiImage>(ui_node).unwrap().texture,
+                images[expected]
+            );
+        }
+    }
+}
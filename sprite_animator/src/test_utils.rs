@@ -0,0 +1,84 @@
+use std::time::Duration;
+
+use bevy::{ecs::event::Event, prelude::*, time::TimeUpdateStrategy};
+
+use crate::{SpriteAnimController, SpriteAnimationPlugin};
+
+/// Time elapsed on every update, matching the default framerate of `SpriteAnimController`
+pub(crate) const STEP: Duration = Duration::from_millis(250);
+
+/// Headless `App` running the `SpriteAnimationPlugin`, without a window or GPU.
+/// Every update advances time by `STEP`, so a controller running at 4 fps displays exactly one
+/// new frame per update.
+pub(crate) struct TestApp {
+    pub app: App,
+}
+
+impl TestApp {
+    pub fn new() -> Self {
+        Self::with_plugin(SpriteAnimationPlugin::default())
+    }
+
+    /// Creates a `TestApp` running a custom `SpriteAnimationPlugin`
+    pub fn with_plugin(plugin: SpriteAnimationPlugin) -> Self {
+        let mut app = App::new();
+        // Assets usually registered by the `DefaultPlugins`
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .init_asset::<Image>()
+            .init_asset::<TextureAtlasLayout>()
+            .add_plugins(plugin)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(STEP));
+        // Time does not advance on the first update
+        app.update();
+        Self { app }
+    }
+
+    /// Spawns an entity animated by `controller`
+    pub fn spawn(&mut self, controller: SpriteAnimController) -> Entity {
+        self.app
+            .world_mut()
+            .spawn((controller, TextureAtlas::default()))
+            .id()
+    }
+
+    /// Runs a single update
+    pub fn step(&mut self) {
+        self.app.update();
+    }
+
+    /// Runs `count` updates and returns the atlas index of `entity` after each of them
+    pub fn frames(&mut self, entity: Entity, count: usize) -> Vec<usize> {
+        (0..count)
+            .map(|_| {
+                self.step();
+                self.atlas_index(entity)
+            })
+            .collect()
+    }
+
+    pub fn atlas_index(&self, entity: Entity) -> usize {
+        self.app
+            .world()
+            .get::<TextureAtlas>(entity)
+            .expect("Entity should have a TextureAtlas")
+            .index
+    }
+
+    pub fn controller_mut(&mut self, entity: Entity) -> Mut<'_, SpriteAnimController> {
+        self.app
+            .world_mut()
+            .get_mut::<SpriteAnimController>(entity)
+            .expect("Entity should have a SpriteAnimController")
+    }
+
+    /// Drains and returns every event of type `E` sent since the last call.
+    /// Events are dropped after two updates, so this should be called after every update that
+    /// might send them.
+    pub fn events<E: Event>(&mut self) -> Vec<E> {
+        self.app
+            .world_mut()
+            .resource_mut::<Events<E>>()
+            .drain()
+            .collect()
+    }
+}